@@ -0,0 +1,182 @@
+use super::{Backend, Lookup, Status};
+use crate::{dump::parse_timestamp, http::Http, CrateInfo, CrateSummary, Error};
+use serde_json::Value;
+use std::{io::Read, time::SystemTime};
+
+pub(crate) const DEFAULT_BASE_URL: &str = "https://crates.io";
+
+/// Larger packages are not downloaded, they are hardly placeholders.
+const MAX_CRATE_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Resolves names using the crates.io JSON API.
+pub(crate) struct Api {
+    http: Http,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Api {
+    pub(crate) fn new(http: Http, base_url: impl Into<String>) -> Self {
+        let mut base_url = base_url.into();
+        while base_url.ends_with('/') {
+            base_url.pop();
+        }
+
+        Self {
+            http,
+            base_url,
+            token: None,
+        }
+    }
+
+    /// Sets the token sent with every request.
+    pub(crate) fn token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+}
+
+impl Api {
+    /// Fetches `path` below the API root. Returns `None` if the resource
+    /// does not exist and `Err(Error::Unresolved)` for unexpected responses.
+    fn get_json(&self, path: &str) -> Result<Option<Value>, Error> {
+        let url = format!("{}/api/v1/{}", self.base_url, path);
+        let resp = self.http.get_authorized(&url, self.token.as_deref())?;
+        match resp.status() {
+            200 => {
+                let body = resp
+                    .into_string()
+                    .map_err(|e| Error::InvalidResponse(e.to_string()))?;
+                serde_json::from_str(&body)
+                    .map(Some)
+                    .map_err(|e| Error::InvalidResponse(e.to_string()))
+            }
+            404 => Ok(None),
+            _ => Err(Error::Unresolved),
+        }
+    }
+}
+
+impl Backend for Api {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        // The API resolves names by their canonical form, the response contains
+        // the spelling of the existing crate.
+        let body = match self.get_json(&format!("crates/{}", name)) {
+            Ok(Some(body)) => body,
+            Ok(None) => return Ok(Status::Free.into()),
+            Err(Error::Unresolved) => return Ok(Status::Unknown.into()),
+            Err(e) => return Err(e),
+        };
+
+        let actual = crate_name(&body)?;
+        let summary = CrateSummary {
+            created_at: timestamp(&body, "/crate/created_at"),
+            downloads: body.pointer("/crate/downloads").and_then(Value::as_u64),
+        };
+        Ok(Lookup::from(Status::Taken(actual)).summary(summary))
+    }
+
+    fn info(&self, name: &str) -> Result<Option<CrateInfo>, Error> {
+        let body = match self.get_json(&format!("crates/{}", name))? {
+            Some(body) => body,
+            None => return Ok(None),
+        };
+
+        let mut info = CrateInfo::new(crate_name(&body)?);
+        let string = |pointer: &str| body.pointer(pointer).and_then(Value::as_str);
+        info.description = string("/crate/description").map(|s| s.trim().to_string());
+        info.repository = string("/crate/repository").map(str::to_string);
+        info.created_at = timestamp(&body, "/crate/created_at");
+        info.updated_at = timestamp(&body, "/crate/updated_at");
+        info.published_at = body
+            .get("versions")
+            .and_then(Value::as_array)
+            .and_then(|versions| {
+                versions
+                    .iter()
+                    .filter_map(|version| version.get("created_at")?.as_str())
+                    .filter_map(parse_timestamp)
+                    .max()
+            });
+        info.maintenance = body
+            .pointer("/crate/badges")
+            .and_then(Value::as_array)
+            .and_then(|badges| {
+                let badge = badges
+                    .iter()
+                    .find(|badge| badge["badge_type"] == "maintenance")?;
+                badge.pointer("/attributes/status")?.as_str()
+            })
+            .map(str::to_string);
+        info.yanked = body
+            .get("versions")
+            .and_then(Value::as_array)
+            .filter(|versions| !versions.is_empty())
+            .map(|versions| versions.iter().all(|version| version["yanked"] == true));
+        info.max_version = string("/crate/max_version")
+            // crates.io reports `0.0.0` if all versions are yanked.
+            .filter(|version| !(*version == "0.0.0" && info.yanked == Some(true)))
+            .map(str::to_string);
+        info.downloads = body.pointer("/crate/downloads").and_then(Value::as_u64);
+        info.recent_downloads = body
+            .pointer("/crate/recent_downloads")
+            .and_then(Value::as_u64);
+
+        // Owners are served by a separate endpoint, which mirrors of the API
+        // may not implement.
+        info.owners = match self.get_json(&format!("crates/{}/owners", info.name)) {
+            Ok(owners) => owners.and_then(|owners| {
+                let users = owners.get("users")?.as_array()?;
+                let logins = users
+                    .iter()
+                    .filter_map(|user| user.get("login")?.as_str())
+                    .map(str::to_string)
+                    .collect();
+                Some(logins)
+            }),
+            Err(Error::Unresolved) => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(Some(info))
+    }
+
+    fn download(&self, name: &str, version: &str) -> Result<Option<Vec<u8>>, Error> {
+        let url = format!(
+            "{}/api/v1/crates/{}/{}/download",
+            self.base_url, name, version
+        );
+        let resp = self.http.get_authorized(&url, self.token.as_deref())?;
+        if resp.status() != 200 {
+            return Err(Error::InvalidResponse(format!(
+                "failed to download {} {}: status {}",
+                name,
+                version,
+                resp.status()
+            )));
+        }
+
+        let mut crate_file = Vec::new();
+        resp.into_reader()
+            .take(MAX_CRATE_FILE_SIZE + 1)
+            .read_to_end(&mut crate_file)
+            .map_err(|e| Error::Transport(e.to_string()))?;
+        if crate_file.len() as u64 > MAX_CRATE_FILE_SIZE {
+            return Ok(None);
+        }
+        Ok(Some(crate_file))
+    }
+}
+
+fn crate_name(body: &Value) -> Result<String, Error> {
+    body.pointer("/crate/name")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| Error::InvalidResponse("missing `crate.name`".to_string()))
+}
+
+fn timestamp(body: &Value, pointer: &str) -> Option<SystemTime> {
+    body.pointer(pointer)
+        .and_then(Value::as_str)
+        .and_then(parse_timestamp)
+}
@@ -0,0 +1,187 @@
+use super::{Backend, Lookup, Status};
+use crate::{
+    cache::{CacheEntry, CacheStore},
+    name::canonical_name,
+    CrateInfo, Error,
+};
+use std::{
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+    time::{Duration, SystemTime},
+};
+
+/// Answers lookups from the result cache, falling back to another backend
+/// for names that are missing or expired.
+///
+/// The cache is best effort: if it can't be read or written, lookups are
+/// passed through to the inner backend.
+pub(crate) struct Cached {
+    inner: Box<dyn Backend>,
+    source: String,
+    path: Option<PathBuf>,
+    ttl: Duration,
+    refresh: bool,
+    store: OnceLock<Option<Mutex<CacheStore>>>,
+}
+
+impl Cached {
+    /// Caches the lookups of `inner`, which queries the registry at `source`.
+    /// If `refresh` is set, cached entries are ignored but still updated.
+    pub(crate) fn new(
+        inner: Box<dyn Backend>,
+        source: impl Into<String>,
+        path: Option<PathBuf>,
+        ttl: Duration,
+        refresh: bool,
+    ) -> Self {
+        Self {
+            inner,
+            source: source.into(),
+            path,
+            ttl,
+            refresh,
+            store: OnceLock::new(),
+        }
+    }
+
+    fn store(&self) -> Option<&Mutex<CacheStore>> {
+        self.store
+            .get_or_init(|| {
+                let mut store = CacheStore::load(self.path.as_ref()?).ok()?;
+                // A cache that can't be compacted is still usable.
+                let _ = store.compact_if_needed(self.ttl);
+                Some(Mutex::new(store))
+            })
+            .as_ref()
+    }
+}
+
+impl Backend for Cached {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        let store = match self.store() {
+            Some(store) => store,
+            None => return self.inner.lookup(name),
+        };
+
+        let canonical = canonical_name(name);
+        if !self.refresh {
+            let store = store.lock().unwrap();
+            if let Some(entry) = store.get(&self.source, &canonical, self.ttl) {
+                return Ok(entry.lookup.clone().stale_since(Some(entry.fetched_at)));
+            }
+        }
+
+        let lookup = self.inner.lookup(name)?;
+        // Only cache definite answers fetched from the registry.
+        if lookup.status != Status::Unknown && lookup.as_of.is_none() {
+            let entry = CacheEntry {
+                fetched_at: SystemTime::now(),
+                lookup: lookup.clone(),
+            };
+            let _ = store
+                .lock()
+                .unwrap()
+                .insert(&self.source, &canonical, entry);
+        }
+        Ok(lookup)
+    }
+
+    /// Metadata is not cached, it is only requested for single names and
+    /// expected to be up to date.
+    fn info(&self, name: &str) -> Result<Option<CrateInfo>, Error> {
+        self.inner.info(name)
+    }
+
+    fn download(&self, name: &str, version: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.inner.download(name, version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::temp_dir, MockBackend};
+    use std::sync::Arc;
+
+    const TTL: Duration = Duration::from_secs(3600);
+
+    fn cached(backend: &Arc<MockBackend>, path: PathBuf, refresh: bool) -> Cached {
+        Cached::new(
+            Box::new(Arc::clone(backend)),
+            "api",
+            Some(path),
+            TTL,
+            refresh,
+        )
+    }
+
+    #[test]
+    fn cached_lookups() {
+        let path = temp_dir("cached").join("results.tsv");
+        let backend = Arc::new(MockBackend::new().taken("serde"));
+
+        let lookup = cached(&backend, path.clone(), false)
+            .lookup("serde")
+            .unwrap();
+        assert_eq!(lookup.as_of, None);
+
+        // A new backend reads the file written by the first one.
+        let cached = cached(&backend, path, false);
+        let lookup = cached.lookup("Serde").unwrap();
+        assert_eq!(lookup.status, Status::Taken("serde".to_string()));
+        assert!(lookup.as_of.is_some());
+        assert_eq!(cached.lookup("foo").unwrap().status, Status::Free);
+        cached.lookup("foo").unwrap();
+        assert_eq!(backend.lookups(), ["serde", "foo"]);
+    }
+
+    #[test]
+    fn refresh_ignores_cached_lookups() {
+        let path = temp_dir("cached-refresh").join("results.tsv");
+        let backend = Arc::new(MockBackend::new());
+        cached(&backend, path.clone(), false).lookup("foo").unwrap();
+
+        let lookup = cached(&backend, path.clone(), true).lookup("foo").unwrap();
+        assert_eq!(lookup.as_of, None);
+        assert_eq!(backend.lookups(), ["foo", "foo"]);
+    }
+
+    #[test]
+    fn unknown_and_stale_lookups_are_not_cached() {
+        let path = temp_dir("cached-unknown").join("results.tsv");
+        let backend = Arc::new(
+            MockBackend::new()
+                .lookup("foo", Status::Unknown.into())
+                .lookup(
+                    "bar",
+                    Lookup::from(Status::Free).stale_since(Some(SystemTime::now())),
+                ),
+        );
+        let cached = cached(&backend, path, false);
+        for _ in 0..2 {
+            cached.lookup("foo").unwrap();
+            cached.lookup("bar").unwrap();
+        }
+        assert_eq!(backend.lookups().len(), 4);
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let path = temp_dir("cached-errors").join("results.tsv");
+        let backend = Arc::new(MockBackend::new().failing("foo", Error::ServerError(503)));
+        let cached = cached(&backend, path, false);
+        assert_eq!(cached.lookup("foo"), Err(Error::ServerError(503)));
+        assert_eq!(cached.lookup("foo"), Err(Error::ServerError(503)));
+    }
+
+    #[test]
+    fn unreadable_cache_is_bypassed() {
+        let dir = temp_dir("cached-unreadable");
+        let backend = Arc::new(MockBackend::new());
+        // A directory can't be read as a cache file.
+        let cached = cached(&backend, dir, false);
+        assert_eq!(cached.lookup("foo").unwrap().status, Status::Free);
+        assert_eq!(cached.lookup("foo").unwrap().status, Status::Free);
+        assert_eq!(backend.lookups().len(), 2);
+    }
+}
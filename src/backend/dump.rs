@@ -0,0 +1,89 @@
+use super::{Backend, Lookup, Status};
+use crate::{dump::DumpStore, name::canonical_name, CrateSummary, Error};
+use std::{path::PathBuf, sync::OnceLock};
+
+/// Resolves names using a store imported from the crates.io database dump.
+///
+/// The store is loaded on the first lookup.
+pub(crate) struct DbDump {
+    path: Option<PathBuf>,
+    store: OnceLock<Result<DumpStore, Error>>,
+}
+
+impl DbDump {
+    pub(crate) fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            store: OnceLock::new(),
+        }
+    }
+}
+
+impl Backend for DbDump {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        let store = self
+            .store
+            .get_or_init(|| match &self.path {
+                Some(path) => DumpStore::load(path),
+                None => Err(Error::Io(
+                    "failed to determine the db dump location".to_string(),
+                )),
+            })
+            .as_ref()
+            .map_err(Clone::clone)?;
+
+        let lookup = match store.crates.get(&canonical_name(name)) {
+            Some(krate) => Lookup::from(Status::Taken(krate.name.clone())).summary(CrateSummary {
+                created_at: krate.created_at,
+                downloads: krate.downloads,
+            }),
+            None => Status::Free.into(),
+        };
+        Ok(lookup.stale_since(Some(store.as_of)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    use std::{
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    #[test]
+    fn lookup() {
+        let path = temp_dir("db-dump-backend").join("db-dump.tsv");
+        fs::write(
+            &path,
+            "# cargo-free crates.io db dump 1600000000\nFoo-Bar\t1500000000\t42\nbaz\t\t\n",
+        )
+        .unwrap();
+        let as_of = Some(UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+
+        let backend = DbDump::new(Some(path));
+        let lookup = backend.lookup("foo_bar").unwrap();
+        assert_eq!(lookup.status, Status::Taken("Foo-Bar".to_string()));
+        assert_eq!(lookup.as_of, as_of);
+        assert_eq!(
+            lookup.summary,
+            Some(CrateSummary {
+                created_at: Some(UNIX_EPOCH + Duration::from_secs(1_500_000_000)),
+                downloads: Some(42),
+            })
+        );
+
+        let lookup = backend.lookup("qux").unwrap();
+        assert_eq!(lookup.status, Status::Free);
+        assert_eq!(lookup.as_of, as_of);
+    }
+
+    #[test]
+    fn missing_store() {
+        let path = temp_dir("db-dump-missing").join("db-dump.tsv");
+        let backend = DbDump::new(Some(path));
+        assert!(matches!(backend.lookup("foo"), Err(Error::Io(_))));
+        assert!(matches!(DbDump::new(None).lookup("foo"), Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,262 @@
+use super::{index, Backend, Lookup, Status};
+use crate::{CrateInfo, Error};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::OnceLock,
+};
+
+pub(crate) const DEFAULT_INDEX_URL: &str = "https://github.com/rust-lang/crates.io-index";
+
+/// Resolves names using a registry index served via the git protocol.
+///
+/// The index is fetched into a bare repository on the first lookup, using
+/// the `git` CLI and thus its credentials and proxy configuration. Later
+/// runs only fetch the changes.
+pub(crate) struct GitIndex {
+    url: String,
+    dir: Option<PathBuf>,
+    fetched: OnceLock<Result<PathBuf, Error>>,
+}
+
+impl GitIndex {
+    /// Creates a backend for the index at `url`, which is fetched into `dir`.
+    pub(crate) fn new(url: impl Into<String>, dir: Option<PathBuf>) -> Self {
+        let url = url.into();
+        Self {
+            url: url.strip_prefix("git+").unwrap_or(&url).to_string(),
+            dir,
+            fetched: OnceLock::new(),
+        }
+    }
+
+    fn fetch(&self) -> Result<PathBuf, Error> {
+        let dir = self
+            .dir
+            .as_ref()
+            .ok_or_else(|| Error::Io("failed to determine the cache directory".to_string()))?;
+        if !dir.join("HEAD").is_file() {
+            fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+            git(dir, &["init", "--quiet", "--bare"])?;
+        }
+
+        let output = git(
+            dir,
+            &["fetch", "--quiet", "--depth", "1", &self.url, "HEAD"],
+        )?;
+        if !output.status.success() {
+            return Err(Error::Transport(format!(
+                "failed to fetch {}: {}",
+                self.url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(dir.clone())
+    }
+}
+
+impl GitIndex {
+    /// Reads the index file of `name` or one of its variants from the fetched
+    /// index.
+    ///
+    /// Returns `None` if the name has too many variants to look up and
+    /// `Some(None)` if no index file exists.
+    fn index_file(&self, name: &str) -> Result<Option<Option<String>>, Error> {
+        let dir = self
+            .fetched
+            .get_or_init(|| self.fetch())
+            .as_ref()
+            .map_err(Clone::clone)?;
+
+        let variants = index::variants(name);
+        for variant in &variants.names {
+            let object = format!("FETCH_HEAD:{}", index::path(variant));
+            let output = git(dir, &["show", &object])?;
+            if output.status.success() {
+                let contents = String::from_utf8_lossy(&output.stdout).into_owned();
+                return Ok(Some(Some(contents)));
+            }
+        }
+
+        // Tell missing files apart from a broken repository, which fails to
+        // show any file.
+        let verified = git(dir, &["rev-parse", "--verify", "--quiet", "FETCH_HEAD"])?;
+        if !verified.status.success() {
+            return Err(Error::Io(format!(
+                "{}: fetched index is not a valid git repository",
+                dir.display()
+            )));
+        }
+        Ok(if variants.complete { Some(None) } else { None })
+    }
+}
+
+impl Backend for GitIndex {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        // The fetched index is complete, so a missing file proves that a name
+        // is free.
+        Ok(match self.index_file(name)? {
+            Some(Some(contents)) => {
+                let actual = index::crate_name(&contents).ok_or_else(index::no_entries)?;
+                Status::Taken(actual).into()
+            }
+            Some(None) => Status::Free.into(),
+            None => Status::Unknown.into(),
+        })
+    }
+
+    fn info(&self, name: &str) -> Result<Option<CrateInfo>, Error> {
+        match self.index_file(name)? {
+            Some(Some(contents)) => index::crate_info(&contents)
+                .map(Some)
+                .ok_or_else(index::no_entries),
+            Some(None) => Ok(None),
+            None => Err(Error::Unresolved),
+        }
+    }
+}
+
+/// Returns the directory the index at `url` is fetched into, inside the
+/// user's cache directory.
+pub(crate) fn default_git_index_dir(url: &str) -> Option<PathBuf> {
+    let name = url
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    dirs::cache_dir().map(|dir| dir.join("cargo-free").join("git").join(name))
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<Output, Error> {
+    Command::new("git")
+        .arg("--git-dir")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| Error::Io(format!("failed to run git: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    /// Creates a git repository at `dir` holding index files for `names`.
+    fn write_index(dir: &Path, names: &[&str]) {
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(["-c", "commit.gpgsign=false"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        fs::create_dir_all(dir).unwrap();
+        git(&["init", "-q"]);
+        for name in names {
+            let file = dir.join(index::path(name));
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(&file, format!(r#"{{"name":"{}","vers":"0.1.0"}}"#, name)).unwrap();
+        }
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "index"]);
+    }
+
+    #[test]
+    fn lookup() {
+        let dir = temp_dir("git-index");
+        write_index(&dir.join("remote"), &["serde_json", "cc"]);
+        let url = format!("git+file://{}", dir.join("remote").display());
+
+        let git_index = GitIndex::new(url, Some(dir.join("fetched")));
+        assert_eq!(
+            git_index.lookup("serde-json").unwrap().status,
+            Status::Taken("serde_json".to_string())
+        );
+        assert_eq!(
+            git_index.lookup("cc").unwrap().status,
+            Status::Taken("cc".to_string())
+        );
+        assert_eq!(git_index.lookup("foo").unwrap().status, Status::Free);
+        assert!(dir.join("fetched").join("HEAD").is_file());
+    }
+
+    #[test]
+    fn failed_fetch() {
+        let dir = temp_dir("git-index-missing");
+        let url = format!("file://{}", dir.join("missing").display());
+        let git_index = GitIndex::new(url, Some(dir.join("fetched")));
+        assert!(matches!(git_index.lookup("foo"), Err(Error::Transport(_))));
+        assert!(matches!(git_index.lookup("bar"), Err(Error::Transport(_))));
+    }
+
+    #[test]
+    fn default_dir_depends_on_url() {
+        let dir = default_git_index_dir("https://example.com/index").unwrap();
+        assert!(dir.ends_with("cargo-free/git/https---example-com-index"));
+    }
+
+    #[test]
+    fn info() {
+        let dir = temp_dir("git-index-info");
+        write_index(&dir.join("remote"), &["serde_json"]);
+        let url = format!("file://{}", dir.join("remote").display());
+
+        let git_index = GitIndex::new(url, Some(dir.join("fetched")));
+        let info = git_index.info("serde-json").unwrap().unwrap();
+        assert_eq!(info.name, "serde_json");
+        assert_eq!(info.max_version.as_deref(), Some("0.1.0"));
+        assert_eq!(git_index.info("foo"), Ok(None));
+    }
+
+    #[test]
+    fn broken_repository() {
+        let dir = temp_dir("git-index-broken");
+        write_index(&dir.join("remote"), &["serde"]);
+        let url = format!("file://{}", dir.join("remote").display());
+
+        let git_index = GitIndex::new(url, Some(dir.join("fetched")));
+        assert_eq!(git_index.lookup("foo").unwrap().status, Status::Free);
+        fs::remove_file(dir.join("fetched").join("FETCH_HEAD")).unwrap();
+        assert!(matches!(git_index.lookup("foo"), Err(Error::Io(_))));
+        assert!(matches!(git_index.info("serde"), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn corrupt_index_file() {
+        let dir = temp_dir("git-index-corrupt");
+        let remote = dir.join("remote");
+        write_index(&remote, &["foo"]);
+        fs::write(remote.join(index::path("foo")), "not json\n").unwrap();
+        write_index(&remote, &[]);
+        let url = format!("file://{}", remote.display());
+
+        let git_index = GitIndex::new(url, Some(dir.join("fetched")));
+        assert!(matches!(
+            git_index.lookup("foo"),
+            Err(Error::InvalidResponse(_))
+        ));
+        assert!(matches!(
+            git_index.info("foo"),
+            Err(Error::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn too_many_variants() {
+        let dir = temp_dir("git-index-variants");
+        write_index(&dir.join("remote"), &[]);
+        let url = format!("file://{}", dir.join("remote").display());
+
+        let git_index = GitIndex::new(url, Some(dir.join("fetched")));
+        assert_eq!(
+            git_index.lookup("a-b-c-d-e-f").unwrap().status,
+            Status::Unknown
+        );
+        assert_eq!(git_index.info("a-b-c-d-e-f"), Err(Error::Unresolved));
+    }
+}
@@ -0,0 +1,217 @@
+//! Helpers shared by all backends reading the registry index format.
+
+use crate::{CrateInfo, Error};
+use semver::Version;
+use serde_json::Value;
+
+/// The maximum number of `-`/`_` variants looked up per name.
+const MAX_VARIANTS: usize = 16;
+
+/// Returns the path of the index file for `name`, relative to the index
+/// root, e.g. `se/rd/serde`.
+pub(crate) fn path(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// The spellings of a name looked up in an index, see [`variants`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Variants {
+    /// The spellings to look up, in order.
+    pub(crate) names: Vec<String>,
+
+    /// Set if `names` contains all spellings sharing the canonical form.
+    /// Otherwise, missing index files don't prove that the name is free.
+    pub(crate) complete: bool,
+}
+
+/// Returns the names that share the canonical form of `name`.
+///
+/// Index files are keyed by the lowercase crate name, so `foo-bar` and
+/// `foo_bar` live in different files. The name as given comes first,
+/// followed by the all-`_` and all-`-` spellings and the remaining
+/// combinations, capped at a fixed number of variants.
+pub(crate) fn variants(name: &str) -> Variants {
+    let name = name.to_ascii_lowercase();
+    let separators = name
+        .char_indices()
+        .filter(|&(_, c)| c == '-' || c == '_')
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let mut variants = Vec::new();
+    let mut candidates = vec![name.clone(), name.replace('-', "_"), name.replace('_', "-")];
+    let combinations = 1usize.checked_shl(separators.len() as u32).unwrap_or(0);
+    candidates.extend((0..combinations).take(MAX_VARIANTS).map(|mask| {
+        let mut variant = name.clone().into_bytes();
+        for (bit, &i) in separators.iter().enumerate() {
+            variant[i] = if mask & (1 << bit) == 0 { b'_' } else { b'-' };
+        }
+        // Only ASCII bytes have been replaced, the name stays valid UTF-8.
+        String::from_utf8(variant).expect("ASCII replacement")
+    }));
+
+    for candidate in candidates {
+        if variants.len() == MAX_VARIANTS {
+            break;
+        }
+        if !variants.contains(&candidate) {
+            variants.push(candidate);
+        }
+    }
+    let complete = separators.len() < usize::BITS as usize && combinations <= MAX_VARIANTS;
+    Variants {
+        names: variants,
+        complete,
+    }
+}
+
+/// Returns the crate name as spelled in an index file, i.e. the `name` of the
+/// last published version. Returns `None` if the file contains no valid
+/// entry.
+pub(crate) fn crate_name(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .rev()
+        .filter(|line| !line.trim().is_empty())
+        .find_map(|line| {
+            let entry: Value = serde_json::from_str(line).ok()?;
+            entry.get("name")?.as_str().map(str::to_string)
+        })
+}
+
+/// Returns the metadata contained in an index file: the crate name, the
+/// highest version that is not yanked and whether all versions are yanked.
+/// Returns `None` if the file contains no valid entry.
+pub(crate) fn crate_info(contents: &str) -> Option<CrateInfo> {
+    let entries = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .collect::<Vec<_>>();
+
+    let mut info = CrateInfo::new(crate_name(contents)?);
+    info.yanked = Some(entries.iter().all(|entry| entry["yanked"] == true));
+    info.max_version = entries
+        .iter()
+        .filter(|entry| entry["yanked"] != true)
+        .filter_map(|entry| Version::parse(entry["vers"].as_str()?).ok())
+        .max()
+        .map(|version| version.to_string());
+    Some(info)
+}
+
+/// The error reported for an index file that exists, but contains no valid
+/// entries.
+pub(crate) fn no_entries() -> Error {
+    Error::InvalidResponse("index file contains no entries".to_string())
+}
+
+/// Returns the crate name stored in a file of Cargo's local index cache
+/// (`.cache` in the index directory).
+///
+/// Such a file starts with a one-byte cache version, a four-byte index
+/// version and a NUL-terminated revision, followed by NUL-terminated pairs of
+/// version and JSON entry.
+pub(crate) fn cached_crate_name(contents: &[u8]) -> Option<String> {
+    let mut fields = contents.get(5..)?.split(|&b| b == 0);
+    // Skip the revision.
+    fields.next()?;
+
+    let entries = fields
+        .skip(1)
+        .step_by(2)
+        .filter_map(|entry| std::str::from_utf8(entry).ok())
+        .collect::<Vec<_>>()
+        .join("\n");
+    crate_name(&entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_depends_on_length() {
+        assert_eq!(path("a"), "1/a");
+        assert_eq!(path("ab"), "2/ab");
+        assert_eq!(path("abc"), "3/a/abc");
+        assert_eq!(path("Serde"), "se/rd/serde");
+    }
+
+    #[test]
+    fn variants_start_with_name() {
+        let variants = variants("Foo-Bar_baz");
+        assert_eq!(
+            variants.names[..3],
+            ["foo-bar_baz", "foo_bar_baz", "foo-bar-baz"]
+        );
+        assert_eq!(variants.names.len(), 4);
+        assert!(variants.names.contains(&"foo_bar-baz".to_string()));
+        assert!(variants.complete);
+    }
+
+    #[test]
+    fn variants_without_separators() {
+        let variants = variants("serde");
+        assert_eq!(variants.names, ["serde"]);
+        assert!(variants.complete);
+    }
+
+    #[test]
+    fn variants_are_capped() {
+        let complete = variants("a-b-c-d-e");
+        assert_eq!(complete.names.len(), 16);
+        assert!(complete.complete);
+        let capped = variants("a-b-c-d-e-f");
+        assert_eq!(capped.names.len(), MAX_VARIANTS);
+        assert!(!capped.complete);
+    }
+
+    #[test]
+    fn crate_name_of_last_entry() {
+        let contents = concat!(
+            r#"{"name":"foo_bar","vers":"0.1.0"}"#,
+            "\n",
+            r#"{"name":"Foo_Bar","vers":"0.2.0"}"#,
+            "\n\n",
+        );
+        assert_eq!(crate_name(contents).as_deref(), Some("Foo_Bar"));
+        assert_eq!(crate_name("not json\n"), None);
+    }
+
+    #[test]
+    fn cached_crate_name_skips_header() {
+        let mut contents = vec![3, 2, 0, 0, 0];
+        contents
+            .extend_from_slice(b"rev\x000.1.0\x00{\"name\":\"foo_bar\",\"vers\":\"0.1.0\"}\x00");
+        assert_eq!(cached_crate_name(&contents).as_deref(), Some("foo_bar"));
+        assert_eq!(cached_crate_name(b"\x03"), None);
+    }
+
+    #[test]
+    fn crate_info_skips_yanked_versions() {
+        let contents = [
+            r#"{"name":"foo","vers":"0.9.0","yanked":false}"#,
+            r#"{"name":"foo","vers":"0.10.0","yanked":false}"#,
+            r#"{"name":"foo","vers":"1.0.0","yanked":true}"#,
+        ]
+        .join("\n");
+        let info = crate_info(&contents).unwrap();
+        assert_eq!(info.name, "foo");
+        assert_eq!(info.max_version.as_deref(), Some("0.10.0"));
+        assert_eq!(info.yanked, Some(false));
+    }
+
+    #[test]
+    fn crate_info_all_yanked() {
+        let info = crate_info(r#"{"name":"foo","vers":"1.0.0","yanked":true}"#).unwrap();
+        assert_eq!(info.max_version, None);
+        assert_eq!(info.yanked, Some(true));
+        assert_eq!(crate_info("\n"), None);
+    }
+}
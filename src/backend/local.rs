@@ -0,0 +1,167 @@
+use super::{index, Backend, Lookup, Status};
+use crate::{name::canonical_name, Error};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::SystemTime,
+};
+
+/// Resolves names using a local registry, a directory containing an index
+/// and the `.crate` files of the crates in it.
+///
+/// Local registries only contain the crates needed by some project, so they
+/// can prove that a name is taken but not that it is free.
+pub(crate) struct LocalRegistry {
+    dir: PathBuf,
+}
+
+impl LocalRegistry {
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Backend for LocalRegistry {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        let index = self.dir.join("index");
+        if !index.is_dir() {
+            return Err(Error::Io(format!(
+                "no local registry index found in {}",
+                self.dir.display()
+            )));
+        }
+
+        for variant in index::variants(name).names {
+            let file = index.join(index::path(&variant));
+            if let Some(actual) = fs::read_to_string(&file)
+                .ok()
+                .and_then(|contents| index::crate_name(&contents))
+            {
+                return Ok(Lookup::from(Status::Taken(actual)).stale_since(modified(&file)));
+            }
+        }
+
+        Ok(Status::Unknown.into())
+    }
+}
+
+/// The vendored crates' names and modification times, keyed by the canonical
+/// name.
+type VendoredCrates = HashMap<String, (String, Option<SystemTime>)>;
+
+/// Resolves names using a directory of vendored crates, as created by
+/// `cargo vendor`.
+///
+/// Like [`LocalRegistry`], it can only prove that a name is taken. The
+/// manifests are read on the first lookup.
+pub(crate) struct VendorDirectory {
+    dir: PathBuf,
+    crates: OnceLock<Result<VendoredCrates, Error>>,
+}
+
+impl VendorDirectory {
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            crates: OnceLock::new(),
+        }
+    }
+
+    /// Reads the package names of all vendored crates.
+    fn read_crates(&self) -> Result<VendoredCrates, Error> {
+        let entries = fs::read_dir(&self.dir).map_err(|e| Error::io(&self.dir, e))?;
+
+        let mut crates = HashMap::new();
+        for entry in entries.filter_map(Result::ok) {
+            let manifest = entry.path().join("Cargo.toml");
+            let name = fs::read_to_string(&manifest)
+                .ok()
+                .and_then(|contents| contents.parse::<toml::Value>().ok())
+                .and_then(|manifest| {
+                    let name = manifest.get("package")?.get("name")?.as_str()?;
+                    Some(name.to_string())
+                });
+            if let Some(name) = name {
+                crates.insert(canonical_name(&name), (name, modified(&manifest)));
+            }
+        }
+        Ok(crates)
+    }
+}
+
+impl Backend for VendorDirectory {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        let crates = self
+            .crates
+            .get_or_init(|| self.read_crates())
+            .as_ref()
+            .map_err(Clone::clone)?;
+
+        Ok(match crates.get(&canonical_name(name)) {
+            Some((actual, as_of)) => {
+                Lookup::from(Status::Taken(actual.clone())).stale_since(*as_of)
+            }
+            None => Status::Unknown.into(),
+        })
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn local_registry() {
+        let dir = temp_dir("local-registry");
+        let file = dir.join("index").join(index::path("serde_json"));
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, r#"{"name":"serde_json","vers":"1.0.0"}"#).unwrap();
+
+        let registry = LocalRegistry::new(&dir);
+        let lookup = registry.lookup("serde-json").unwrap();
+        assert_eq!(lookup.status, Status::Taken("serde_json".to_string()));
+        assert_eq!(lookup.as_of, modified(&file));
+        // Local registries can't prove that a name is free.
+        assert_eq!(registry.lookup("foo").unwrap().status, Status::Unknown);
+    }
+
+    #[test]
+    fn missing_local_registry() {
+        let registry = LocalRegistry::new(temp_dir("local-registry-missing"));
+        assert!(matches!(registry.lookup("foo"), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn vendor_directory() {
+        let dir = temp_dir("vendor-directory");
+        fs::create_dir_all(dir.join("serde_json")).unwrap();
+        fs::write(
+            dir.join("serde_json").join("Cargo.toml"),
+            "[package]\nname = \"serde_json\"\nversion = \"1.0.0\"",
+        )
+        .unwrap();
+        // Directories without a valid manifest are skipped.
+        fs::create_dir_all(dir.join("invalid")).unwrap();
+        fs::write(dir.join("invalid").join("Cargo.toml"), "[package").unwrap();
+
+        let vendor = VendorDirectory::new(&dir);
+        let lookup = vendor.lookup("Serde-JSON").unwrap();
+        assert_eq!(lookup.status, Status::Taken("serde_json".to_string()));
+        assert!(lookup.as_of.is_some());
+        assert_eq!(vendor.lookup("invalid").unwrap().status, Status::Unknown);
+    }
+
+    #[test]
+    fn missing_vendor_directory() {
+        let dir = temp_dir("vendor-directory-missing").join("vendor");
+        let vendor = VendorDirectory::new(dir);
+        assert!(matches!(vendor.lookup("foo"), Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Backend, Lookup, Status};
+use crate::{name::canonical_name, CrateSummary, Error};
+use std::{collections::HashMap, sync::Mutex};
+
+/// An in-memory backend for tests, seeded with taken names and failures.
+///
+/// Names are matched by their canonical form. Names that were neither marked
+/// as taken nor as failing are reported as free.
+///
+/// ```
+/// use cargo_free::{Availability, Checker, Error, MockBackend};
+/// use std::sync::Arc;
+///
+/// let backend = Arc::new(
+///     MockBackend::new()
+///         .taken("serde")
+///         .failing("tokio", Error::ServerError(503)),
+/// );
+/// let checker = Checker::builder().build_with(Arc::clone(&backend));
+///
+/// assert_eq!(checker.check_availability("Serde"), Ok(Availability::Unavailable));
+/// assert_eq!(checker.check_availability("foo"), Ok(Availability::Available));
+/// assert_eq!(checker.check_availability("tokio"), Err(Error::ServerError(503)));
+/// assert_eq!(backend.lookups(), ["Serde", "foo", "tokio"]);
+/// ```
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    results: HashMap<String, Result<Lookup, Error>>,
+    fallback: Option<Result<Lookup, Error>>,
+    lookups: Mutex<Vec<String>>,
+}
+
+impl MockBackend {
+    /// Creates a backend reporting every name as free.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks `name` as taken, using `name` as the crate's spelling.
+    pub fn taken(self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        self.lookup(name.clone(), Status::Taken(name).into())
+    }
+
+    /// Marks `name` as taken by a crate with the given summary.
+    pub fn taken_with_summary(self, name: impl Into<String>, summary: CrateSummary) -> Self {
+        let name = name.into();
+        self.lookup(
+            name.clone(),
+            Lookup::from(Status::Taken(name)).summary(summary),
+        )
+    }
+
+    /// Makes looking up `name` return the given lookup result.
+    pub fn lookup(mut self, name: impl AsRef<str>, lookup: Lookup) -> Self {
+        self.results.insert(canonical_name(name), Ok(lookup));
+        self
+    }
+
+    /// Makes looking up `name` fail with `error`.
+    pub fn failing(mut self, name: impl AsRef<str>, error: Error) -> Self {
+        self.results.insert(canonical_name(name), Err(error));
+        self
+    }
+
+    /// Makes looking up any name not seeded otherwise fail with `error`, e.g.
+    /// to simulate an outage.
+    pub fn failing_all(mut self, error: Error) -> Self {
+        self.fallback = Some(Err(error));
+        self
+    }
+
+    /// Returns the names looked up so far, in order.
+    pub fn lookups(&self) -> Vec<String> {
+        self.lookups.lock().unwrap().clone()
+    }
+}
+
+impl Backend for MockBackend {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        self.lookups.lock().unwrap().push(name.to_string());
+        self.results
+            .get(&canonical_name(name))
+            .or(self.fallback.as_ref())
+            .cloned()
+            .unwrap_or_else(|| Ok(Status::Free.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Availability, Checker};
+    use std::{
+        sync::Arc,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    #[test]
+    fn seeded_results() {
+        let summary = CrateSummary {
+            created_at: Some(UNIX_EPOCH + Duration::from_secs(1_500_000_000)),
+            downloads: Some(42),
+        };
+        let backend = MockBackend::new()
+            .taken_with_summary("Foo-Bar", summary.clone())
+            .lookup("baz", Status::Unknown.into())
+            .failing("qux", Error::ServerError(503));
+
+        let lookup = Backend::lookup(&backend, "foo_bar").unwrap();
+        assert_eq!(lookup.status, Status::Taken("Foo-Bar".to_string()));
+        assert_eq!(lookup.summary, Some(summary));
+        assert_eq!(
+            Backend::lookup(&backend, "baz").unwrap().status,
+            Status::Unknown
+        );
+        assert_eq!(
+            Backend::lookup(&backend, "qux"),
+            Err(Error::ServerError(503))
+        );
+        assert_eq!(
+            Backend::lookup(&backend, "other").unwrap().status,
+            Status::Free
+        );
+        assert_eq!(backend.lookups(), ["foo_bar", "baz", "qux", "other"]);
+    }
+
+    #[test]
+    fn failing_all() {
+        let backend = MockBackend::new()
+            .taken("serde")
+            .failing_all(Error::Transport("offline".to_string()));
+        assert!(Backend::lookup(&backend, "serde").is_ok());
+        assert_eq!(
+            Backend::lookup(&backend, "foo"),
+            Err(Error::Transport("offline".to_string()))
+        );
+    }
+
+    /// Counts the lookups passed on to another backend.
+    struct Counting(Box<dyn Backend>, Arc<Mutex<usize>>);
+
+    impl Backend for Counting {
+        fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+            *self.1.lock().unwrap() += 1;
+            self.0.lookup(name)
+        }
+    }
+
+    #[test]
+    fn decorated_backend() {
+        let count = Arc::new(Mutex::new(0));
+        let checker = Checker::builder().build_with(Counting(
+            Box::new(MockBackend::new().taken("serde")),
+            Arc::clone(&count),
+        ));
+        assert_eq!(
+            checker.check_availability("serde"),
+            Ok(Availability::Unavailable)
+        );
+        assert_eq!(
+            checker.check_availability("std"),
+            Ok(Availability::Reserved)
+        );
+        assert_eq!(*count.lock().unwrap(), 1);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{CrateInfo, CrateSummary, Error};
+use std::{sync::Arc, time::SystemTime};
+
+mod api;
+mod cached;
+mod dump;
+mod git;
+mod index;
+mod local;
+mod mock;
+mod offline;
+mod snapshot;
+mod sparse;
+
+pub use self::mock::MockBackend;
+
+pub(crate) use self::{
+    api::{Api, DEFAULT_BASE_URL as API_BASE_URL},
+    cached::Cached,
+    dump::DbDump,
+    git::{default_git_index_dir, GitIndex, DEFAULT_INDEX_URL as GIT_INDEX_URL},
+    local::{LocalRegistry, VendorDirectory},
+    offline::Offline,
+    snapshot::SnapshotBackend,
+    sparse::{SparseIndex, TokenSource, DEFAULT_INDEX_URL as SPARSE_INDEX_URL},
+};
+
+/// The data source a [`Checker`](crate::Checker) queries to resolve crate
+/// names.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum BackendKind {
+    /// The crates.io JSON API (`/api/v1/crates/{name}`).
+    #[default]
+    Api,
+
+    /// A registry index served via the sparse protocol, by default
+    /// `https://index.crates.io`. Cheaper than the API and supported by any
+    /// sparse registry.
+    SparseIndex,
+
+    /// A registry index served via the git protocol. The index is fetched
+    /// into the user's cache directory using the `git` CLI.
+    GitIndex,
+
+    /// The crates.io index data cached locally by Cargo under
+    /// `$CARGO_HOME/registry/index`. Works without network access, but
+    /// answers may be outdated and names Cargo never resolved can't be
+    /// answered unless a full git index is present.
+    Offline,
+
+    /// A local store imported from the crates.io database dump, see
+    /// [`import_db_dump`](crate::import_db_dump). Answers are as recent as
+    /// the imported dump.
+    DbDump,
+
+    /// A compact [`Snapshot`](crate::Snapshot) of all crate names, loaded
+    /// into memory. The fastest backend for bulk checks.
+    Snapshot,
+}
+
+/// Whether a name is in use, as seen by a backend.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Status {
+    /// No crate uses the name or any of its variants.
+    Free,
+
+    /// A crate uses the name. Contains the crate's actual spelling, which
+    /// may differ from the looked up name in case and `-`/`_`.
+    Taken(String),
+
+    /// The backend could not determine whether the name is in use.
+    Unknown,
+}
+
+/// The result of looking up a single name in a backend.
+///
+/// Create one from a [`Status`] and attach further data using the builder
+/// methods:
+///
+/// ```
+/// use cargo_free::{CrateSummary, Lookup, Status};
+///
+/// let lookup = Lookup::from(Status::Taken("serde".to_string())).summary(CrateSummary {
+///     downloads: Some(1_000),
+///     ..CrateSummary::default()
+/// });
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Lookup {
+    /// Whether the name is in use.
+    pub status: Status,
+
+    /// The time the underlying data was last updated, if the answer stems
+    /// from local data that may be outdated.
+    pub as_of: Option<SystemTime>,
+
+    /// Facts about the crate occupying the name, if the backend knows any.
+    pub summary: Option<CrateSummary>,
+}
+
+impl Lookup {
+    /// Marks the lookup as answered from local data last updated at `as_of`.
+    pub fn stale_since(mut self, as_of: Option<SystemTime>) -> Self {
+        self.as_of = as_of;
+        self
+    }
+
+    /// Attaches facts about the crate occupying the name.
+    pub fn summary(mut self, summary: CrateSummary) -> Self {
+        self.summary = Some(summary);
+        self
+    }
+}
+
+impl From<Status> for Lookup {
+    fn from(status: Status) -> Self {
+        Self {
+            status,
+            as_of: None,
+            summary: None,
+        }
+    }
+}
+
+/// A source that can resolve the availability of a single crate name.
+///
+/// The built-in backends are selected via [`BackendKind`]. Custom backends,
+/// e.g. a [`MockBackend`] in tests, are passed to
+/// [`CheckerBuilder::build_with`](crate::CheckerBuilder::build_with).
+pub trait Backend: Send + Sync {
+    /// Looks up `name`. The name is guaranteed to be valid and crates using a
+    /// name with the same canonical form must be reported as taken.
+    fn lookup(&self, name: &str) -> Result<Lookup, Error>;
+
+    /// Looks up the metadata of the crate using `name`, or `None` if the name
+    /// is free.
+    ///
+    /// The default implementation only reports what [`lookup`](Backend::lookup)
+    /// knows and fails with [`Error::Unresolved`] if the status is unknown.
+    fn info(&self, name: &str) -> Result<Option<CrateInfo>, Error> {
+        let lookup = self.lookup(name)?;
+        match lookup.status {
+            Status::Free => Ok(None),
+            Status::Taken(actual) => {
+                let mut info = CrateInfo::new(actual);
+                if let Some(summary) = lookup.summary {
+                    info.created_at = summary.created_at;
+                    info.downloads = summary.downloads;
+                }
+                Ok(Some(info))
+            }
+            Status::Unknown => Err(Error::Unresolved),
+        }
+    }
+
+    /// Downloads the published package (`.crate` file) of `version` of the
+    /// crate `name`. Returns `None` if the backend can't provide packages,
+    /// which is the default.
+    fn download(&self, name: &str, version: &str) -> Result<Option<Vec<u8>>, Error> {
+        let _ = (name, version);
+        Ok(None)
+    }
+}
+
+/// Fails every lookup, used if a backend could not be configured.
+pub(crate) struct Misconfigured(pub(crate) Error);
+
+impl Backend for Misconfigured {
+    fn lookup(&self, _name: &str) -> Result<Lookup, Error> {
+        Err(self.0.clone())
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for Box<B> {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        (**self).lookup(name)
+    }
+
+    fn info(&self, name: &str) -> Result<Option<CrateInfo>, Error> {
+        (**self).info(name)
+    }
+
+    fn download(&self, name: &str, version: &str) -> Result<Option<Vec<u8>>, Error> {
+        (**self).download(name, version)
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for Arc<B> {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        (**self).lookup(name)
+    }
+
+    fn info(&self, name: &str) -> Result<Option<CrateInfo>, Error> {
+        (**self).info(name)
+    }
+
+    fn download(&self, name: &str, version: &str) -> Result<Option<Vec<u8>>, Error> {
+        (**self).download(name, version)
+    }
+}
@@ -0,0 +1,288 @@
+use super::{index, Backend, Lookup, Status};
+use crate::Error;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
+
+/// Prefixes of the directories Cargo stores the crates.io index in, for the
+/// sparse and the git protocol respectively.
+const CRATES_IO_PREFIXES: &[&str] = &["index.crates.io-", "github.com-1ecc6299db9ec823"];
+
+/// The ref Cargo fetches the git index into.
+const GIT_INDEX_REF: &str = "refs/remotes/origin/HEAD";
+
+/// Resolves names using the index data Cargo keeps under
+/// `$CARGO_HOME/registry/index`.
+///
+/// Cargo caches the index files of all crates it resolved in `.cache`. Those
+/// can only prove that a name is taken. A git index checkout contains every
+/// crate and can also prove that a name is free.
+pub(crate) struct Offline {
+    cargo_home: Option<PathBuf>,
+}
+
+impl Offline {
+    pub(crate) fn new(cargo_home: Option<PathBuf>) -> Self {
+        Self { cargo_home }
+    }
+
+    fn index_dirs(&self) -> Result<Vec<PathBuf>, Error> {
+        let cargo_home = self
+            .cargo_home
+            .as_ref()
+            .ok_or_else(|| Error::Io("failed to determine the Cargo home directory".to_string()))?;
+        let root = cargo_home.join("registry").join("index");
+        let entries = fs::read_dir(&root).map_err(|e| Error::io(&root, e))?;
+
+        let dirs = entries
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                CRATES_IO_PREFIXES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+            })
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        if dirs.is_empty() {
+            return Err(Error::Io(format!(
+                "no crates.io index found in {}",
+                root.display()
+            )));
+        }
+
+        Ok(dirs)
+    }
+}
+
+impl Backend for Offline {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        let variants = index::variants(name);
+        let mut free_as_of = None;
+        for dir in self.index_dirs()? {
+            let mut complete = variants.complete;
+            for variant in &variants.names {
+                let path = index::path(variant);
+                if let Some((actual, as_of)) = read_cache(&dir, &path) {
+                    return Ok(Lookup::from(Status::Taken(actual)).stale_since(Some(as_of)));
+                }
+
+                match read_git(&dir, &path) {
+                    Some(Some(contents)) => {
+                        if let Some(actual) = index::crate_name(&contents) {
+                            return Ok(
+                                Lookup::from(Status::Taken(actual)).stale_since(git_updated(&dir))
+                            );
+                        }
+                    }
+                    Some(None) => {}
+                    None => complete = false,
+                }
+            }
+
+            if complete {
+                free_as_of = Some(git_updated(&dir));
+            }
+        }
+
+        Ok(match free_as_of {
+            Some(as_of) => Lookup::from(Status::Free).stale_since(as_of),
+            None => Status::Unknown.into(),
+        })
+    }
+}
+
+/// Reads `path` from Cargo's index cache in `dir`, returning the crate name
+/// and the time the file was cached.
+fn read_cache(dir: &Path, path: &str) -> Option<(String, SystemTime)> {
+    let file = dir.join(".cache").join(path);
+    let contents = fs::read(&file).ok()?;
+    let actual = index::cached_crate_name(&contents)?;
+    let modified = fs::metadata(&file).and_then(|m| m.modified()).ok()?;
+    Some((actual, modified))
+}
+
+/// Reads `path` from the git index checkout in `dir` using the `git` CLI.
+///
+/// Returns `None` if `dir` contains no usable git index and `Some(None)` if
+/// the file does not exist in it.
+fn read_git(dir: &Path, path: &str) -> Option<Option<String>> {
+    let git_dir = dir.join(".git");
+    if !git_dir.is_dir() {
+        return None;
+    }
+
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(&git_dir)
+        .arg("show")
+        .arg(format!("{}:{}", GIT_INDEX_REF, path))
+        .output()
+        .ok()?;
+    if output.status.success() {
+        return Some(Some(String::from_utf8_lossy(&output.stdout).into_owned()));
+    }
+
+    // Tell a missing file apart from a missing or broken repository.
+    let verified = Command::new("git")
+        .arg("--git-dir")
+        .arg(&git_dir)
+        .args(["rev-parse", "--verify", "--quiet", GIT_INDEX_REF])
+        .output()
+        .ok()?;
+    if verified.status.success() {
+        Some(None)
+    } else {
+        None
+    }
+}
+
+/// Returns the time Cargo last updated the git index in `dir`.
+fn git_updated(dir: &Path) -> Option<SystemTime> {
+    [
+        dir.join(".last-updated"),
+        dir.join(".git").join("FETCH_HEAD"),
+    ]
+    .iter()
+    .find_map(|file| fs::metadata(file).and_then(|m| m.modified()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    use std::{fs::File, time::Duration};
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    /// Writes a file of Cargo's index cache for `name`, modified at `modified`.
+    fn write_cache(dir: &Path, name: &str, modified: SystemTime) {
+        let file = dir.join(".cache").join(index::path(name));
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        let mut contents = vec![3, 2, 0, 0, 0];
+        contents.extend_from_slice(b"rev\0");
+        contents.extend_from_slice(b"0.1.0\0");
+        contents.extend_from_slice(format!(r#"{{"name":"{}","vers":"0.1.0"}}"#, name).as_bytes());
+        contents.push(0);
+        fs::write(&file, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    /// Creates a git index checkout containing `names`, last updated at
+    /// `updated`.
+    fn write_git(dir: &Path, names: &[&str], updated: SystemTime) {
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(["-c", "commit.gpgsign=false"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        for name in names {
+            let file = dir.join(index::path(name));
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(&file, format!(r#"{{"name":"{}","vers":"0.1.0"}}"#, name)).unwrap();
+        }
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "index"]);
+        git(&["update-ref", GIT_INDEX_REF, "HEAD"]);
+        let last_updated = File::create(dir.join(".last-updated")).unwrap();
+        last_updated.set_modified(updated).unwrap();
+    }
+
+    fn index_dir(cargo_home: &Path, name: &str) -> PathBuf {
+        let dir = cargo_home.join("registry").join("index").join(name);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn cached_names_are_taken() {
+        let cargo_home = temp_dir("offline-cache");
+        let dir = index_dir(&cargo_home, "index.crates.io-6f17d22bba15001f");
+        write_cache(&dir, "foo_bar", at(1_600_000_000));
+
+        let offline = Offline::new(Some(cargo_home));
+        let lookup = offline.lookup("Foo-Bar").unwrap();
+        assert_eq!(lookup.status, Status::Taken("foo_bar".to_string()));
+        assert_eq!(lookup.as_of, Some(at(1_600_000_000)));
+    }
+
+    #[test]
+    fn cache_cant_prove_free_names() {
+        let cargo_home = temp_dir("offline-cache-only");
+        let dir = index_dir(&cargo_home, "index.crates.io-6f17d22bba15001f");
+        write_cache(&dir, "serde", at(1_600_000_000));
+
+        let offline = Offline::new(Some(cargo_home));
+        assert_eq!(offline.lookup("foo").unwrap().status, Status::Unknown);
+    }
+
+    #[test]
+    fn git_checkout_proves_free_names() {
+        let cargo_home = temp_dir("offline-git");
+        let dir = index_dir(&cargo_home, "github.com-1ecc6299db9ec823");
+        write_git(&dir, &["foo_bar"], at(1_700_000_000));
+
+        let offline = Offline::new(Some(cargo_home));
+        let lookup = offline.lookup("foo-bar").unwrap();
+        assert_eq!(lookup.status, Status::Taken("foo_bar".to_string()));
+        assert_eq!(lookup.as_of, Some(at(1_700_000_000)));
+
+        let lookup = offline.lookup("foo").unwrap();
+        assert_eq!(lookup.status, Status::Free);
+        assert_eq!(lookup.as_of, Some(at(1_700_000_000)));
+    }
+
+    #[test]
+    fn names_with_untried_variants_are_unknown() {
+        let cargo_home = temp_dir("offline-variants");
+        let dir = index_dir(&cargo_home, "github.com-1ecc6299db9ec823");
+        write_git(&dir, &[], at(1_700_000_000));
+
+        let offline = Offline::new(Some(cargo_home));
+        assert_eq!(offline.lookup("a-b-c-d-e").unwrap().status, Status::Free);
+        assert_eq!(
+            offline.lookup("a-b-c-d-e-f").unwrap().status,
+            Status::Unknown
+        );
+    }
+
+    #[test]
+    fn broken_git_checkout() {
+        let cargo_home = temp_dir("offline-broken-git");
+        let dir = index_dir(&cargo_home, "github.com-1ecc6299db9ec823");
+        write_git(&dir, &["foo"], at(1_700_000_000));
+        fs::remove_file(dir.join(".git").join(GIT_INDEX_REF)).unwrap();
+
+        let offline = Offline::new(Some(cargo_home));
+        assert_eq!(offline.lookup("bar").unwrap().status, Status::Unknown);
+    }
+
+    #[test]
+    fn other_registries_are_ignored() {
+        let cargo_home = temp_dir("offline-other");
+        let dir = index_dir(&cargo_home, "example.com-0123456789abcdef");
+        write_cache(&dir, "foo", at(1_600_000_000));
+
+        let offline = Offline::new(Some(cargo_home));
+        assert!(matches!(offline.lookup("foo"), Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,73 @@
+use super::{Backend, Lookup, Status};
+use crate::{snapshot::Snapshot, Error};
+use std::{path::PathBuf, sync::OnceLock};
+
+/// Resolves names using a [`Snapshot`] of all crate names.
+///
+/// The snapshot is loaded on the first lookup.
+pub(crate) struct SnapshotBackend {
+    path: Option<PathBuf>,
+    snapshot: OnceLock<Result<Snapshot, Error>>,
+}
+
+impl SnapshotBackend {
+    pub(crate) fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            snapshot: OnceLock::new(),
+        }
+    }
+}
+
+impl Backend for SnapshotBackend {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        let snapshot = self
+            .snapshot
+            .get_or_init(|| match &self.path {
+                Some(path) => Snapshot::load(path),
+                None => Err(Error::Io(
+                    "failed to determine the snapshot location".to_string(),
+                )),
+            })
+            .as_ref()
+            .map_err(Clone::clone)?;
+
+        let status = match snapshot.get(name) {
+            Some(actual) => Status::Taken(actual),
+            None => Status::Free,
+        };
+        Ok(Lookup::from(status).stale_since(Some(snapshot.as_of())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn lookup() {
+        let path = temp_dir("snapshot-backend").join("names.fst");
+        let as_of = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        Snapshot::from_names(["foo-bar"], as_of)
+            .unwrap()
+            .save(&path)
+            .unwrap();
+
+        let backend = SnapshotBackend::new(Some(path));
+        let lookup = backend.lookup("Foo_Bar").unwrap();
+        assert_eq!(lookup.status, Status::Taken("foo-bar".to_string()));
+        assert_eq!(lookup.as_of, Some(as_of));
+        assert_eq!(backend.lookup("foo").unwrap().status, Status::Free);
+    }
+
+    #[test]
+    fn missing_snapshot() {
+        let path = temp_dir("snapshot-backend-missing").join("names.fst");
+        assert!(matches!(
+            SnapshotBackend::new(Some(path)).lookup("foo"),
+            Err(Error::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+use super::{index, Backend, Lookup, Status};
+use crate::{http::Http, CrateInfo, Error};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+pub(crate) const DEFAULT_INDEX_URL: &str = "https://index.crates.io";
+
+/// Obtains the token of a registry, e.g. by running a credential provider.
+pub(crate) type TokenSource = Box<dyn Fn() -> Result<Option<String>, Error> + Send + Sync>;
+
+/// Resolves names using the sparse registry protocol, fetching the index
+/// file of each name over HTTP.
+///
+/// Like Cargo, a token is only obtained and sent if the registry's
+/// `config.json` requires authentication.
+pub(crate) struct SparseIndex {
+    http: Http,
+    index_url: String,
+    token_source: Option<TokenSource>,
+    token: OnceLock<Result<Option<String>, Error>>,
+}
+
+impl SparseIndex {
+    pub(crate) fn new(http: Http, index_url: impl Into<String>) -> Self {
+        let index_url = index_url.into();
+        let mut index_url = index_url
+            .strip_prefix("sparse+")
+            .unwrap_or(&index_url)
+            .to_string();
+        while index_url.ends_with('/') {
+            index_url.pop();
+        }
+
+        Self {
+            http,
+            index_url,
+            token_source: None,
+            token: OnceLock::new(),
+        }
+    }
+
+    /// Sets how to obtain the token sent to registries requiring
+    /// authentication.
+    pub(crate) fn token_source(mut self, token_source: Option<TokenSource>) -> Self {
+        self.token_source = token_source;
+        self
+    }
+}
+
+impl SparseIndex {
+    /// Returns the token to send, obtaining it on first use if the registry
+    /// requires authentication.
+    fn token(&self) -> Result<Option<&str>, Error> {
+        let token_source = match &self.token_source {
+            Some(token_source) => token_source,
+            None => return Ok(None),
+        };
+        self.token
+            .get_or_init(|| {
+                if self.auth_required()? {
+                    token_source()
+                } else {
+                    Ok(None)
+                }
+            })
+            .as_ref()
+            .map(Option::as_deref)
+            .map_err(Clone::clone)
+    }
+
+    /// Returns `true` if the registry's `config.json` sets `auth-required`.
+    fn auth_required(&self) -> Result<bool, Error> {
+        let url = format!("{}/config.json", self.index_url);
+        match self.http.get(&url) {
+            // Registries requiring authentication may deny anonymous access
+            // to their configuration, too.
+            Err(Error::Unauthorized(_)) => Ok(true),
+            Err(e) => Err(e),
+            Ok(resp) if resp.status() == 200 => {
+                let config = resp
+                    .into_string()
+                    .ok()
+                    .and_then(|body| serde_json::from_str::<Value>(&body).ok())
+                    .ok_or_else(|| {
+                        Error::InvalidResponse(format!("{}: invalid registry configuration", url))
+                    })?;
+                Ok(config["auth-required"] == true)
+            }
+            Ok(_) => Ok(false),
+        }
+    }
+
+    /// Fetches the index file of `name` or one of its variants.
+    ///
+    /// Returns `None` if the registry sent an unexpected response or the name
+    /// has too many variants to look up, and `Some(None)` if no index file
+    /// exists.
+    fn index_file(&self, name: &str) -> Result<Option<Option<String>>, Error> {
+        let token = self.token()?;
+        let variants = index::variants(name);
+        for variant in &variants.names {
+            let url = format!("{}/{}", self.index_url, index::path(variant));
+            let resp = self.http.get_authorized(&url, token)?;
+            match resp.status() {
+                200 => {
+                    let contents = resp
+                        .into_string()
+                        .map_err(|e| Error::InvalidResponse(e.to_string()))?;
+                    return Ok(Some(Some(contents)));
+                }
+                // Registries may answer with any of these for missing files.
+                404 | 410 | 451 => continue,
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(if variants.complete { Some(None) } else { None })
+    }
+}
+
+impl Backend for SparseIndex {
+    fn lookup(&self, name: &str) -> Result<Lookup, Error> {
+        Ok(match self.index_file(name)? {
+            Some(Some(contents)) => {
+                let actual = index::crate_name(&contents).ok_or_else(index::no_entries)?;
+                Status::Taken(actual).into()
+            }
+            Some(None) => Status::Free.into(),
+            None => Status::Unknown.into(),
+        })
+    }
+
+    fn info(&self, name: &str) -> Result<Option<CrateInfo>, Error> {
+        match self.index_file(name)? {
+            Some(Some(contents)) => index::crate_info(&contents)
+                .map(Some)
+                .ok_or_else(index::no_entries),
+            Some(None) => Ok(None),
+            None => Err(Error::Unresolved),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::Status,
+        http::{HttpSettings, RateLimit, RetryPolicy},
+        testing::{Response, Server},
+    };
+    use std::time::Duration;
+
+    fn index(url: &str) -> SparseIndex {
+        let http = Http::new(HttpSettings {
+            timeout: Duration::from_secs(5),
+            user_agent: String::new(),
+            retry: RetryPolicy::none(),
+            rate_limit: RateLimit::none(),
+            proxy: None,
+            ca_bundle: None,
+        });
+        SparseIndex::new(http, format!("sparse+{}/", url))
+    }
+
+    fn secret() -> Option<TokenSource> {
+        Some(Box::new(|| Ok(Some("secret".to_string()))))
+    }
+
+    #[test]
+    fn token_is_sent_if_required() {
+        let server = Server::new(vec![
+            (
+                "/config.json",
+                Response::ok(r#"{"dl":"x","auth-required":true}"#),
+            ),
+            ("/3/f/foo", Response::ok(r#"{"name":"foo","vers":"0.1.0"}"#)),
+        ]);
+        let index = index(server.url()).token_source(secret());
+        index.lookup("foo").unwrap();
+        index.lookup("foo").unwrap();
+
+        let requests = server.requests();
+        assert_eq!(server.paths(), ["/config.json", "/3/f/foo", "/3/f/foo"]);
+        assert!(!requests[0].headers.contains_key("authorization"));
+        assert_eq!(requests[1].headers["authorization"], "secret");
+        assert_eq!(requests[2].headers["authorization"], "secret");
+    }
+
+    #[test]
+    fn token_is_sent_if_config_is_protected() {
+        let server = Server::new(vec![
+            ("/config.json", Response::status(401)),
+            ("/3/f/foo", Response::status(404)),
+        ]);
+        let index = index(server.url()).token_source(secret());
+        assert_eq!(index.lookup("foo").unwrap().status, Status::Free);
+        assert_eq!(server.requests()[1].headers["authorization"], "secret");
+    }
+
+    #[test]
+    fn token_is_not_obtained_unless_required() {
+        let server = Server::new(vec![
+            ("/config.json", Response::ok(r#"{"dl":"x"}"#)),
+            ("/3/f/foo", Response::status(404)),
+        ]);
+        let index = index(server.url()).token_source(Some(Box::new(|| {
+            Err(Error::Credential("test".to_string()))
+        })));
+        assert_eq!(index.lookup("foo").unwrap().status, Status::Free);
+        assert!(!server.requests()[1].headers.contains_key("authorization"));
+    }
+
+    #[test]
+    fn failing_token_source() {
+        let server = Server::new(vec![(
+            "/config.json",
+            Response::ok(r#"{"dl":"x","auth-required":true}"#),
+        )]);
+        let index = index(server.url()).token_source(Some(Box::new(|| {
+            Err(Error::Credential("test".to_string()))
+        })));
+        assert_eq!(
+            index.lookup("foo"),
+            Err(Error::Credential("test".to_string()))
+        );
+        assert_eq!(server.paths(), ["/config.json"]);
+    }
+
+    #[test]
+    fn taken_name() {
+        let server = Server::new(vec![(
+            "/fo/o_/foo_bar",
+            Response::ok(r#"{"name":"foo_bar","vers":"0.1.0"}"#),
+        )]);
+        assert_eq!(
+            index(server.url()).lookup("Foo-Bar").unwrap().status,
+            Status::Taken("foo_bar".to_string())
+        );
+        assert_eq!(server.paths(), ["/fo/o-/foo-bar", "/fo/o_/foo_bar"]);
+    }
+
+    #[test]
+    fn free_name() {
+        let server = Server::new(vec![("/3/f/foo", Response::status(410))]);
+        assert_eq!(
+            index(server.url()).lookup("foo").unwrap().status,
+            Status::Free
+        );
+    }
+
+    #[test]
+    fn unexpected_response() {
+        let server = Server::new(vec![("/3/f/foo", Response::status(400))]);
+        assert_eq!(
+            index(server.url()).lookup("foo").unwrap().status,
+            Status::Unknown
+        );
+    }
+
+    #[test]
+    fn empty_index_file() {
+        let server = Server::new(vec![("/3/f/foo", Response::ok("\n"))]);
+        assert!(matches!(
+            index(server.url()).lookup("foo"),
+            Err(Error::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn info() {
+        let server = Server::new(vec![
+            (
+                "/3/f/foo",
+                Response::ok(
+                    "{\"name\":\"foo\",\"vers\":\"0.1.0\"}\n{\"name\":\"foo\",\"vers\":\"0.2.0\"}",
+                ),
+            ),
+            ("/3/b/bar", Response::status(400)),
+        ]);
+        let index = index(server.url());
+        let info = index.info("foo").unwrap().unwrap();
+        assert_eq!(info.max_version.as_deref(), Some("0.2.0"));
+        assert_eq!(index.info("baz"), Ok(None));
+        assert_eq!(index.info("bar"), Err(Error::Unresolved));
+    }
+
+    #[test]
+    fn too_many_variants() {
+        let server = Server::new(vec![]);
+        let index = index(server.url());
+        assert_eq!(index.lookup("a-b-c-d-e-f").unwrap().status, Status::Unknown);
+        assert_eq!(index.info("a-b-c-d-e-f"), Err(Error::Unresolved));
+        assert_eq!(server.paths().len(), 2 * 16);
+    }
+}
@@ -0,0 +1,437 @@
+//! A persistent cache of lookup results, so that repeatedly checking the same
+//! names does not query the registry every time.
+//!
+//! The cache is a text file with one line per lookup. New results are
+//! appended, later lines override earlier ones for the same name. Once most
+//! lines are overridden or expired, the file is rewritten.
+
+use crate::{
+    backend::{Lookup, Status},
+    CrateSummary, Error,
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The first line of every cache file.
+const CACHE_HEADER: &str = "# cargo-free result cache";
+
+/// The number of overridden or expired lines from which on the cache file is
+/// rewritten, if they outnumber the lines still in use.
+const COMPACT_LINES: usize = 1000;
+
+/// A cached lookup result.
+#[derive(Clone, Debug)]
+pub(crate) struct CacheEntry {
+    pub(crate) fetched_at: SystemTime,
+    pub(crate) lookup: Lookup,
+}
+
+impl CacheEntry {
+    /// Returns `true` if the entry is older than `ttl`.
+    fn is_expired(&self, ttl: Duration) -> bool {
+        SystemTime::now()
+            .duration_since(self.fetched_at)
+            .is_ok_and(|age| age >= ttl)
+    }
+}
+
+/// The cached lookups, keyed by registry and canonical name.
+#[derive(Debug)]
+pub(crate) struct CacheStore {
+    path: PathBuf,
+    entries: HashMap<(String, String), CacheEntry>,
+    /// The number of entry lines in the cache file, including overridden
+    /// ones.
+    lines: usize,
+}
+
+impl CacheStore {
+    /// Loads the cache at `path`. A missing file results in an empty cache.
+    pub(crate) fn load(path: &Path) -> Result<Self, Error> {
+        let mut store = Self {
+            path: path.to_path_buf(),
+            entries: HashMap::new(),
+            lines: 0,
+        };
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(store),
+            Err(e) => return Err(Error::io(path, e)),
+        };
+
+        let mut lines = BufReader::new(file).lines();
+        let header = lines
+            .next()
+            .transpose()
+            .map_err(|e| Error::io(path, e))?
+            .unwrap_or_default();
+        if header != CACHE_HEADER {
+            return Err(Error::Io(format!("{}: not a result cache", path.display())));
+        }
+
+        for line in lines {
+            let line = line.map_err(|e| Error::io(path, e))?;
+            if let Some((key, entry)) = parse_line(&line) {
+                store.entries.insert(key, entry);
+                store.lines += 1;
+            }
+        }
+        Ok(store)
+    }
+
+    /// Returns the entry for `canonical` looked up in `source`, if it is not
+    /// older than `ttl`.
+    pub(crate) fn get(&self, source: &str, canonical: &str, ttl: Duration) -> Option<&CacheEntry> {
+        self.entries
+            .get(&(source.to_string(), canonical.to_string()))
+            .filter(|entry| !entry.is_expired(ttl))
+    }
+
+    /// Adds an entry and appends it to the cache file.
+    pub(crate) fn insert(
+        &mut self,
+        source: &str,
+        canonical: &str,
+        entry: CacheEntry,
+    ) -> Result<(), Error> {
+        self.append(source, canonical, &entry)
+            .map_err(|e| Error::io(&self.path, e))?;
+        self.entries
+            .insert((source.to_string(), canonical.to_string()), entry);
+        self.lines += 1;
+        Ok(())
+    }
+
+    /// Rewrites the cache file like [`CacheStore::compact`] if most of its
+    /// lines are overridden or older than `ttl`. Returns `true` if the file
+    /// was rewritten.
+    pub(crate) fn compact_if_needed(&mut self, ttl: Duration) -> Result<bool, Error> {
+        let used = self
+            .entries
+            .values()
+            .filter(|entry| !entry.is_expired(ttl))
+            .count();
+        let unused = self.lines - used;
+        if unused < COMPACT_LINES || unused <= used {
+            return Ok(false);
+        }
+        self.compact(ttl).map(|()| true)
+    }
+
+    /// Drops the entries older than `ttl` and rewrites the cache file with
+    /// one line per remaining entry.
+    ///
+    /// The new file replaces the old one at once, so concurrent runs read
+    /// either of them. Results appended by them meanwhile are lost, which
+    /// only costs another lookup.
+    pub(crate) fn compact(&mut self, ttl: Duration) -> Result<(), Error> {
+        self.entries.retain(|_, entry| !entry.is_expired(ttl));
+        let mut keys = self.entries.keys().collect::<Vec<_>>();
+        keys.sort();
+        let mut contents = format!("{}\n", CACHE_HEADER);
+        for key in keys {
+            contents.push_str(&format_line(&key.0, &key.1, &self.entries[key]));
+        }
+
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(format!(".{}.tmp", std::process::id()));
+        let temp = PathBuf::from(temp);
+        fs::write(&temp, contents)
+            .and_then(|()| fs::rename(&temp, &self.path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&temp);
+                Error::io(&self.path, e)
+            })?;
+        self.lines = self.entries.len();
+        Ok(())
+    }
+
+    fn append(&self, source: &str, canonical: &str, entry: &CacheEntry) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut line = String::new();
+        if file.metadata()?.len() == 0 {
+            line.push_str(CACHE_HEADER);
+            line.push('\n');
+        }
+
+        line.push_str(&format_line(source, canonical, entry));
+        // Write the line at once, so concurrent checks don't interleave.
+        file.write_all(line.as_bytes())
+    }
+}
+
+/// Statistics about the result cache, see [`cache_stats`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CacheStats {
+    /// The number of cached names.
+    pub entries: usize,
+
+    /// The number of cached names older than the TTL.
+    pub expired: usize,
+
+    /// The size of the cache file in bytes.
+    pub size: u64,
+}
+
+/// Returns the default location of the result cache, inside the user's cache
+/// directory (e.g. `~/.cache/cargo-free/results.tsv` on Linux).
+pub fn default_cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("cargo-free").join("results.tsv"))
+}
+
+/// Removes all cached results from the cache at `path`.
+pub fn clear_cache(path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(Error::io(path, e)),
+        _ => Ok(()),
+    }
+}
+
+/// Returns statistics about the cache at `path`. Entries older than `ttl`
+/// are counted as expired.
+pub fn cache_stats(path: impl AsRef<Path>, ttl: Duration) -> Result<CacheStats, Error> {
+    let path = path.as_ref();
+    let store = CacheStore::load(path)?;
+    Ok(CacheStats {
+        entries: store.entries.len(),
+        expired: store
+            .entries
+            .values()
+            .filter(|entry| entry.is_expired(ttl))
+            .count(),
+        size: fs::metadata(path).map_or(0, |metadata| metadata.len()),
+    })
+}
+
+fn parse_line(line: &str) -> Option<((String, String), CacheEntry)> {
+    let mut fields = line.split('\t');
+    let source = fields.next()?.to_string();
+    let canonical = fields.next()?.to_string();
+    let fetched_at = UNIX_EPOCH + Duration::from_secs(fields.next()?.parse().ok()?);
+    let status = match (fields.next()?, fields.next()?) {
+        ("free", _) => Status::Free,
+        ("taken", spelling) => Status::Taken(spelling.to_string()),
+        _ => return None,
+    };
+    let summary = CrateSummary {
+        created_at: fields
+            .next()
+            .and_then(|secs| secs.parse().ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+        downloads: fields.next().and_then(|downloads| downloads.parse().ok()),
+    };
+
+    let mut lookup = Lookup::from(status);
+    if summary != CrateSummary::default() {
+        lookup = lookup.summary(summary);
+    }
+    Some(((source, canonical), CacheEntry { fetched_at, lookup }))
+}
+
+fn format_line(source: &str, canonical: &str, entry: &CacheEntry) -> String {
+    let (status, spelling) = match &entry.lookup.status {
+        Status::Taken(spelling) => ("taken", spelling.as_str()),
+        _ => ("free", ""),
+    };
+    let summary = entry.lookup.summary.clone().unwrap_or_default();
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        source,
+        canonical,
+        unix_secs(entry.fetched_at),
+        status,
+        spelling,
+        summary
+            .created_at
+            .map(|time| unix_secs(time).to_string())
+            .unwrap_or_default(),
+        summary.downloads.map(|d| d.to_string()).unwrap_or_default(),
+    )
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    fn entry(lookup: Lookup) -> CacheEntry {
+        CacheEntry {
+            fetched_at: UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+            lookup,
+        }
+    }
+
+    #[test]
+    fn parse_line_fields() {
+        let (key, entry) =
+            parse_line("api\tfoo_bar\t1600000000\ttaken\tFoo-Bar\t1500000000\t42").unwrap();
+        assert_eq!(key, ("api".to_string(), "foo_bar".to_string()));
+        assert_eq!(
+            entry.fetched_at,
+            UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+        );
+        assert_eq!(entry.lookup.status, Status::Taken("Foo-Bar".to_string()));
+        assert_eq!(
+            entry.lookup.summary,
+            Some(CrateSummary {
+                created_at: Some(UNIX_EPOCH + Duration::from_secs(1_500_000_000)),
+                downloads: Some(42),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_line_without_summary() {
+        let (_, entry) = parse_line("api\tfoo\t1600000000\tfree\t\t\t").unwrap();
+        assert_eq!(entry.lookup, Lookup::from(Status::Free));
+        assert!(parse_line("api\tfoo\t1600000000\tunknown\t").is_none());
+        assert!(parse_line("api\tfoo").is_none());
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_dir("cache").join("results.tsv");
+        let taken = Lookup::from(Status::Taken("Foo".to_string())).summary(CrateSummary {
+            created_at: Some(UNIX_EPOCH + Duration::from_secs(1_500_000_000)),
+            downloads: Some(7),
+        });
+        let mut store = CacheStore::load(&path).unwrap();
+        store.insert("api", "foo", entry(taken.clone())).unwrap();
+        store
+            .insert("api", "bar", entry(Lookup::from(Status::Free)))
+            .unwrap();
+        store
+            .insert("sparse", "foo", entry(Lookup::from(Status::Free)))
+            .unwrap();
+
+        let store = CacheStore::load(&path).unwrap();
+        let ttl = Duration::from_secs(u64::MAX / 2);
+        assert_eq!(store.get("api", "foo", ttl).unwrap().lookup, taken);
+        assert_eq!(
+            store.get("api", "bar", ttl).unwrap().lookup.status,
+            Status::Free
+        );
+        assert_eq!(
+            store.get("sparse", "foo", ttl).unwrap().lookup.status,
+            Status::Free
+        );
+        assert!(store.get("api", "baz", ttl).is_none());
+        assert!(store.get("api", "foo", Duration::from_secs(1)).is_none());
+    }
+
+    #[test]
+    fn later_lines_override_earlier_ones() {
+        let path = temp_dir("cache-override").join("results.tsv");
+        fs::write(
+            &path,
+            format!(
+                "{}\napi\tfoo\t1600000000\tfree\t\t\t\napi\tfoo\t1600000001\ttaken\tfoo\t\t\n",
+                CACHE_HEADER
+            ),
+        )
+        .unwrap();
+        let store = CacheStore::load(&path).unwrap();
+        let entry = store.get("api", "foo", Duration::from_secs(u64::MAX / 2));
+        assert_eq!(
+            entry.unwrap().lookup.status,
+            Status::Taken("foo".to_string())
+        );
+    }
+
+    #[test]
+    fn compact() {
+        let path = temp_dir("cache-compact").join("results.tsv");
+        let now = unix_secs(SystemTime::now());
+        let mut contents = format!("{}\n", CACHE_HEADER);
+        for i in 0..COMPACT_LINES {
+            contents.push_str(&format!("api\tfoo\t{}\tfree\t\t\t\n", now - i as u64));
+        }
+        contents.push_str("api\told\t1600000000\tfree\t\t\t\n");
+        contents.push_str(&format!("api\tbar\t{}\ttaken\tBar\t\t7\n", now));
+        fs::write(&path, contents).unwrap();
+
+        let ttl = Duration::from_secs(3600);
+        let mut store = CacheStore::load(&path).unwrap();
+        assert!(store.compact_if_needed(ttl).unwrap());
+        assert!(!store.compact_if_needed(ttl).unwrap());
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents,
+            format!(
+                "{}\napi\tbar\t{}\ttaken\tBar\t\t7\napi\tfoo\t{}\tfree\t\t\t\n",
+                CACHE_HEADER,
+                now,
+                now - COMPACT_LINES as u64 + 1
+            )
+        );
+        let store = CacheStore::load(&path).unwrap();
+        assert_eq!(store.entries.len(), 2);
+        assert!(store
+            .get("api", "old", Duration::from_secs(u64::MAX / 2))
+            .is_none());
+    }
+
+    #[test]
+    fn compact_only_when_most_lines_are_unused() {
+        let path = temp_dir("cache-compact-used").join("results.tsv");
+        let now = unix_secs(SystemTime::now());
+        let mut contents = format!("{}\n", CACHE_HEADER);
+        for i in 0..COMPACT_LINES * 3 {
+            contents.push_str(&format!("api\tfoo{}\t{}\tfree\t\t\t\n", i % 1500, now));
+        }
+        fs::write(&path, &contents).unwrap();
+
+        let mut store = CacheStore::load(&path).unwrap();
+        assert!(!store.compact_if_needed(Duration::from_secs(3600)).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+    }
+
+    #[test]
+    fn load_rejects_other_files() {
+        let path = temp_dir("cache-invalid").join("results.tsv");
+        fs::write(&path, "api\tfoo\t1600000000\tfree\t\t\t\n").unwrap();
+        assert!(matches!(CacheStore::load(&path), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn stats_and_clear() {
+        let path = temp_dir("cache-stats").join("results.tsv");
+        let mut store = CacheStore::load(&path).unwrap();
+        store
+            .insert("api", "foo", entry(Lookup::from(Status::Free)))
+            .unwrap();
+        let fresh = CacheEntry {
+            fetched_at: SystemTime::now(),
+            lookup: Lookup::from(Status::Free),
+        };
+        store.insert("api", "bar", fresh).unwrap();
+
+        let stats = cache_stats(&path, Duration::from_secs(3600)).unwrap();
+        assert_eq!((stats.entries, stats.expired), (2, 1));
+        assert!(stats.size > 0);
+
+        clear_cache(&path).unwrap();
+        assert!(!path.exists());
+        // Clearing a missing cache is fine.
+        clear_cache(&path).unwrap();
+    }
+}
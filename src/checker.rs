@@ -0,0 +1,1292 @@
+use crate::{
+    backend::{self, Backend, BackendKind, Lookup, Status},
+    cache::default_cache_path,
+    config::{CargoConfig, ReplacementSource, SourceReplacement},
+    confusable::{ascii_skeleton, confusables, Confusable},
+    credentials,
+    dump::default_db_dump_path,
+    http::{self, Http, HttpSettings, RateLimit, RetryPolicy},
+    name::{canonical_name, is_reserved, validate_name},
+    snapshot::default_snapshot_path,
+    squat, Availability, CrateInfo, Error, SquatReport,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 5;
+const DEFAULT_JOBS: usize = 4;
+/// The name Cargo uses to refer to crates.io as a registry.
+const CRATES_IO: &str = "crates-io";
+
+/// The outcome of checking a single crate name.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Check {
+    /// The checked name, as passed in.
+    pub name: String,
+
+    /// The canonical form of the name, see [`canonical_name`].
+    pub canonical: String,
+
+    /// The availability of the name.
+    pub availability: Availability,
+
+    /// The spelling of the crate occupying the name, if it is taken. Differs
+    /// from `name` if a crate uses another case or `-`/`_` variant.
+    pub taken_as: Option<String>,
+
+    /// Set if the answer stems from local data that may be outdated, e.g. in
+    /// offline mode. Contains the time the data was last updated.
+    pub as_of: Option<SystemTime>,
+
+    /// Facts about the crate occupying the name, if it is taken and the
+    /// backend knows them.
+    pub summary: Option<CrateSummary>,
+
+    /// The name of the source that answered instead of the registry, if the
+    /// registry is replaced by a mirror via Cargo's `[source]` table.
+    pub source: Option<String>,
+
+    /// The non-ASCII characters of the name resembling ASCII ones, e.g. a
+    /// Cyrillic `а`.
+    pub confusables: Vec<Confusable>,
+
+    /// The result of checking the ASCII name the name resembles, see
+    /// [`ascii_skeleton`]. Only set for names containing non-ASCII
+    /// characters that all resemble ASCII ones. Failing to check the ASCII
+    /// name doesn't affect the result for the name itself.
+    pub skeleton: Option<Box<Result<Check, Error>>>,
+}
+
+/// Basic facts about an existing crate.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CrateSummary {
+    /// The time the crate was first published.
+    pub created_at: Option<SystemTime>,
+
+    /// The total number of downloads.
+    pub downloads: Option<u64>,
+}
+
+/// A reusable, configured availability checker.
+///
+/// All checks performed by the same checker share one HTTP agent and thus
+/// its connection pool.
+///
+/// ```no_run
+/// use cargo_free::{Availability, Checker};
+/// use std::time::Duration;
+///
+/// let checker = Checker::builder()
+///     .base_url("http://localhost:8080")
+///     .timeout(Duration::from_secs(1))
+///     .build();
+/// assert_eq!(checker.check_availability("serde"), Ok(Availability::Unavailable));
+/// ```
+pub struct Checker {
+    backend: Box<dyn Backend>,
+    source: Option<String>,
+    crates_io: bool,
+    reserved: HashSet<String>,
+    jobs: usize,
+}
+
+impl Checker {
+    /// Creates a checker using the default configuration.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Returns a builder to configure a new checker.
+    pub fn builder() -> CheckerBuilder {
+        CheckerBuilder::default()
+    }
+
+    /// Checks the availability for a given crate name.
+    ///
+    /// # Arguments
+    ///
+    /// - `name`: The crate name to check
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Availability)` if the name could be resolved. If the crate name is
+    /// empty, `Err(Error::EmptyCrateName)` gets returned. Names violating the
+    /// crates.io naming rules result in `Ok(Availability::Invalid)` without
+    /// any network request being made, the same goes for reserved names and
+    /// `Ok(Availability::Reserved)`. Network failures are reported using the
+    /// remaining `Error` variants, e.g. `Err(Error::RateLimited)` if the
+    /// registry throttled the request.
+    pub fn check_availability(&self, name: impl AsRef<str>) -> Result<Availability, Error> {
+        self.check_name(name.as_ref(), false, &|name| self.backend.lookup(name))
+            .map(|check| check.availability)
+    }
+
+    /// Checks a given crate name, like [`Checker::check_availability`], but
+    /// also reports its canonical form and the spelling of the crate that
+    /// occupies it.
+    ///
+    /// Names containing confusable characters, e.g. a Cyrillic `а`, are
+    /// invalid. If all their non-ASCII characters resemble ASCII ones, the
+    /// resembled ASCII name is checked as well and reported as `skeleton`.
+    ///
+    /// ```no_run
+    /// use cargo_free::{Availability, Checker};
+    ///
+    /// let check = Checker::new().check("Serde").unwrap();
+    /// assert_eq!(check.canonical, "serde");
+    /// assert_eq!(check.availability, Availability::Unavailable);
+    /// assert_eq!(check.taken_as.as_deref(), Some("serde"));
+    /// ```
+    pub fn check(&self, name: impl AsRef<str>) -> Result<Check, Error> {
+        self.check_name(name.as_ref(), true, &|name| self.backend.lookup(name))
+    }
+
+    /// Checks `name`, and its ASCII skeleton if `skeleton` is set. Names
+    /// that are valid and not reserved are resolved using `lookup`.
+    fn check_name(
+        &self,
+        name: &str,
+        skeleton: bool,
+        lookup: &dyn Fn(&str) -> Result<Lookup, Error>,
+    ) -> Result<Check, Error> {
+        if name.is_empty() {
+            return Err(Error::EmptyCrateName);
+        }
+
+        let canonical = canonical_name(name);
+        let mut taken_as = None;
+        let mut as_of = None;
+        let mut summary = None;
+        let mut source = None;
+        let mut skeleton_check = None;
+        let availability = if let Err(reason) = validate_name(name) {
+            if let Some(ascii) = ascii_skeleton(name).filter(|_| skeleton) {
+                skeleton_check = Some(Box::new(self.check_name(&ascii, false, lookup)));
+            }
+            Availability::Invalid(reason)
+        } else if self.is_reserved(name, &canonical) {
+            Availability::Reserved
+        } else {
+            let lookup = lookup(name)?;
+            as_of = lookup.as_of;
+            summary = lookup.summary;
+            source = self.source.clone();
+            match lookup.status {
+                Status::Free => Availability::Available,
+                Status::Taken(actual) => {
+                    taken_as = Some(actual);
+                    Availability::Unavailable
+                }
+                Status::Unknown => Availability::Unknown,
+            }
+        };
+
+        Ok(Check {
+            name: name.to_string(),
+            canonical,
+            availability,
+            taken_as,
+            as_of,
+            summary,
+            source,
+            confusables: confusables(name),
+            skeleton: skeleton_check,
+        })
+    }
+
+    /// Returns `true` if `name` is reserved by crates.io or the checker's
+    /// configuration.
+    fn is_reserved(&self, name: &str, canonical: &str) -> bool {
+        (self.crates_io && is_reserved(name)) || self.reserved.contains(canonical)
+    }
+
+    /// Returns the name the backend has to look up to check `name`: `name`
+    /// itself, its ASCII skeleton if it is invalid, or none if it is resolved
+    /// without the backend.
+    fn lookup_name(&self, name: &str) -> Option<String> {
+        let name = match validate_name(name) {
+            Ok(()) => name.to_string(),
+            Err(_) => ascii_skeleton(name).filter(|ascii| validate_name(ascii).is_ok())?,
+        };
+        Some(name).filter(|name| !self.is_reserved(name, &canonical_name(name)))
+    }
+
+    /// Looks up the metadata of the crate using `name` or one of its
+    /// variants. Returns `Ok(None)` if the name is free. Invalid and reserved
+    /// names are reported as `Err(Error::InvalidCrateName)` and
+    /// `Err(Error::ReservedCrateName)` without consulting the backend.
+    ///
+    /// How much is known depends on the backend, the crates.io API reports
+    /// all fields of [`CrateInfo`].
+    ///
+    /// ```no_run
+    /// use cargo_free::Checker;
+    ///
+    /// let info = Checker::new().lookup("serde").unwrap().unwrap();
+    /// println!("{:?} by {:?}", info.max_version, info.owners);
+    /// ```
+    pub fn lookup(&self, name: impl AsRef<str>) -> Result<Option<CrateInfo>, Error> {
+        let name = name.as_ref();
+        if name.is_empty() {
+            return Err(Error::EmptyCrateName);
+        }
+        if let Err(reason) = validate_name(name) {
+            return Err(Error::InvalidCrateName(reason));
+        }
+        if self.is_reserved(name, &canonical_name(name)) {
+            return Err(Error::ReservedCrateName);
+        }
+
+        self.backend.info(name)
+    }
+
+    /// Rates how likely the crate described by `info` was published only to
+    /// reserve its name, like [`CrateInfo::squat_report`]. If the backend
+    /// provides packages, the package of the highest version is downloaded
+    /// and checked for code, too.
+    ///
+    /// ```no_run
+    /// use cargo_free::Checker;
+    ///
+    /// let checker = Checker::new();
+    /// if let Some(info) = checker.lookup("foo").unwrap() {
+    ///     let report = checker.squat_report(&info).unwrap();
+    ///     println!("squat score {}: {:?}", report.score, report.signals);
+    /// }
+    /// ```
+    pub fn squat_report(&self, info: &CrateInfo) -> Result<SquatReport, Error> {
+        let crate_file = match &info.max_version {
+            Some(version) => self.backend.download(&info.name, version)?,
+            None => None,
+        };
+        match crate_file {
+            Some(crate_file) => squat::inspect_crate_file(info, &crate_file),
+            None => Ok(info.squat_report()),
+        }
+    }
+
+    /// Checks many crate names at once, running up to
+    /// [`jobs`](CheckerBuilder::jobs) checks concurrently.
+    ///
+    /// Names sharing the same canonical form are only looked up once, but
+    /// each spelling is validated on its own. The results are returned in the
+    /// order of `names`.
+    ///
+    /// ```no_run
+    /// use cargo_free::Checker;
+    ///
+    /// let checker = Checker::builder().jobs(8).build();
+    /// for result in checker.check_many(&["serde", "Serde", "foo-bar"]) {
+    ///     println!("{:?}", result);
+    /// }
+    /// ```
+    pub fn check_many<I, S>(&self, names: I) -> Vec<Result<Check, Error>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let names = names
+            .into_iter()
+            .map(|name| name.as_ref().to_string())
+            .collect::<Vec<_>>();
+
+        // Lookups only depend on the canonical form, so looking up one
+        // spelling per canonical name suffices.
+        let mut unique = Vec::new();
+        let mut seen = HashSet::new();
+        for name in names.iter().filter_map(|name| self.lookup_name(name)) {
+            if seen.insert(canonical_name(&name)) {
+                unique.push(name);
+            }
+        }
+
+        let next = AtomicUsize::new(0);
+        let lookups = Mutex::new(HashMap::new());
+        thread::scope(|scope| {
+            for _ in 0..self.jobs.clamp(1, unique.len().max(1)) {
+                scope.spawn(|| {
+                    while let Some(name) = unique.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let lookup = self.backend.lookup(name);
+                        lookups.lock().unwrap().insert(canonical_name(name), lookup);
+                    }
+                });
+            }
+        });
+
+        let lookups = lookups.into_inner().unwrap();
+        names
+            .iter()
+            .map(|name| self.check_name(name, true, &|name| lookups[&canonical_name(name)].clone()))
+            .collect()
+    }
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A builder for [`Checker`].
+#[derive(Clone, Debug)]
+pub struct CheckerBuilder {
+    backend: BackendKind,
+    base_url: Option<String>,
+    registry: Option<String>,
+    cargo_home: Option<PathBuf>,
+    db_dump_path: Option<PathBuf>,
+    snapshot_path: Option<PathBuf>,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+    ca_bundle: Option<PathBuf>,
+    user_agent: Option<String>,
+    contact: Option<String>,
+    token: Option<String>,
+    retry: RetryPolicy,
+    rate_limit: Option<RateLimit>,
+    reserved: HashSet<String>,
+    jobs: usize,
+    cache_ttl: Option<Duration>,
+    cache_path: Option<PathBuf>,
+    refresh_cache: bool,
+}
+
+impl CheckerBuilder {
+    /// Sets the backend used to resolve crate names. Defaults to
+    /// [`BackendKind::Api`].
+    pub fn backend(mut self, backend: BackendKind) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets the base URL of the registry to query, e.g. a staging instance or
+    /// a local mock. Defaults to `https://crates.io` for the API and
+    /// `https://index.crates.io` for the sparse index backend.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Checks names against the alternative registry `name` instead of
+    /// crates.io. Its index URL is read from the `[registries]` table of
+    /// Cargo's configuration, sparse and git indices are supported. Overrides
+    /// [`backend`](CheckerBuilder::backend) and
+    /// [`base_url`](CheckerBuilder::base_url).
+    ///
+    /// The names reserved by crates.io are not reported as
+    /// [`Availability::Reserved`] for alternative registries. Like in Cargo,
+    /// the name `crates-io` refers to crates.io itself.
+    pub fn registry(mut self, name: impl Into<String>) -> Self {
+        self.registry = Some(name.into()).filter(|name| name != CRATES_IO);
+        self
+    }
+
+    /// Sets the Cargo home directory whose registry cache is used by
+    /// [`BackendKind::Offline`]. Defaults to `$CARGO_HOME` or `~/.cargo`.
+    pub fn cargo_home(mut self, cargo_home: impl Into<PathBuf>) -> Self {
+        self.cargo_home = Some(cargo_home.into());
+        self
+    }
+
+    /// Sets the location of the store used by [`BackendKind::DbDump`].
+    /// Defaults to [`default_db_dump_path`].
+    pub fn db_dump_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.db_dump_path = Some(path.into());
+        self
+    }
+
+    /// Sets the location of the snapshot used by [`BackendKind::Snapshot`].
+    /// Defaults to [`default_snapshot_path`].
+    pub fn snapshot_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.snapshot_path = Some(path.into());
+        self
+    }
+
+    /// Sets the timeout after which a single request gets aborted. Defaults
+    /// to Cargo's `http.timeout` setting or five seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the proxy used for all requests, e.g. `http://proxy:3128`.
+    /// Defaults to Cargo's `http.proxy` setting, falling back to the
+    /// `HTTPS_PROXY`, `https_proxy` and `http_proxy` environment variables.
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Sets a PEM file with the CA certificates used to verify the registry's
+    /// TLS certificate, replacing the bundled ones. Defaults to Cargo's
+    /// `http.cainfo` setting.
+    pub fn ca_bundle(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_bundle = Some(path.into());
+        self
+    }
+
+    /// Sets the `User-Agent` header sent alongside each request. Defaults to
+    /// [`default_user_agent`](crate::default_user_agent).
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Sets the contact information, e.g. an email address, included in the
+    /// default `User-Agent`, so registry operators can reach out instead of
+    /// blocking bulk checks.
+    pub fn contact(mut self, contact: impl Into<String>) -> Self {
+        self.contact = Some(contact.into());
+        self
+    }
+
+    /// Sets the token sent in the `Authorization` header, e.g. for a private
+    /// registry rejecting anonymous requests. For registries selected via
+    /// [`registry`](CheckerBuilder::registry), the token is obtained like
+    /// Cargo does by default: from `CARGO_REGISTRIES_<NAME>_TOKEN`,
+    /// `credentials.toml` or the configured credential providers.
+    ///
+    /// Sparse indexes only receive the token if their `config.json` requires
+    /// authentication, the token of a registry is only obtained then.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Sets the policy used to retry failed requests. Defaults to
+    /// [`RetryPolicy::none`].
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the rate limit for requests to the registry. Defaults to
+    /// [`RateLimit::crates_io`] for the API and [`RateLimit::none`] for the
+    /// sparse index, which is served by a CDN.
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Adds names that should be reported as [`Availability::Reserved`] on
+    /// top of the bundled list, e.g. if crates.io reserved new names since
+    /// this crate was released.
+    pub fn reserved_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.reserved
+            .extend(names.into_iter().map(|name| canonical_name(name.as_ref())));
+        self
+    }
+
+    /// Sets the maximum number of concurrent checks performed by
+    /// [`Checker::check_many`]. Defaults to four.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Enables the persistent result cache. Results fetched from the registry
+    /// are reused for `ttl` and reported as possibly stale, see
+    /// [`Check::as_of`]. Only the network backends are cached.
+    pub fn cache(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the location of the result cache. Defaults to
+    /// [`default_cache_path`].
+    pub fn cache_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache_path = Some(path.into());
+        self
+    }
+
+    /// Ignores cached results, but still updates the cache with the fetched
+    /// ones.
+    pub fn refresh_cache(mut self, refresh: bool) -> Self {
+        self.refresh_cache = refresh;
+        self
+    }
+
+    /// Creates the configured checker.
+    pub fn build(self) -> Checker {
+        let config = CargoConfig::load();
+        let replacement = self.replacement(&config);
+        Checker {
+            source: match &replacement {
+                Ok(Some(replacement)) => Some(replacement.name.clone()),
+                _ => None,
+            },
+            backend: self.backend_from(&config, replacement),
+            crates_io: self.registry.is_none(),
+            reserved: self.reserved,
+            jobs: self.jobs,
+        }
+    }
+
+    /// Creates the configured checker, resolving names using a custom
+    /// `backend` instead of the configured [`BackendKind`].
+    pub fn build_with(self, backend: impl Backend + 'static) -> Checker {
+        Checker {
+            backend: Box::new(backend),
+            source: None,
+            crates_io: self.registry.is_none(),
+            reserved: self.reserved,
+            jobs: self.jobs,
+        }
+    }
+
+    /// Creates the configured built-in backend without wrapping it into a
+    /// checker, e.g. to decorate it with a custom [`Backend`].
+    ///
+    /// Like Cargo, the registry is replaced by the source configured via
+    /// `source.crates-io.replace-with` (or `source.<registry>.replace-with`
+    /// for alternative registries), e.g. a mirror. Sparse and git indices,
+    /// local registries and directories of vendored crates are supported as
+    /// replacements. The latter two only contain some crates and thus can't
+    /// prove that a name is free.
+    pub fn build_backend(&self) -> Box<dyn Backend> {
+        let config = CargoConfig::load();
+        self.backend_from(&config, self.replacement(&config))
+    }
+
+    /// Returns the source replacing the queried registry. Explicit base URLs
+    /// and local data are never replaced.
+    fn replacement(&self, config: &CargoConfig) -> Result<Option<SourceReplacement>, Error> {
+        match &self.registry {
+            Some(name) => config.source_replacement(name),
+            None if self.base_url.is_none()
+                && matches!(
+                    self.backend,
+                    BackendKind::Api | BackendKind::SparseIndex | BackendKind::GitIndex
+                ) =>
+            {
+                config.source_replacement(CRATES_IO)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn backend_from(
+        &self,
+        config: &CargoConfig,
+        replacement: Result<Option<SourceReplacement>, Error>,
+    ) -> Box<dyn Backend> {
+        // Never fall back to the replaced registry, it may be unreachable or
+        // must not learn about the checked names.
+        let replacement = match replacement {
+            Ok(replacement) => replacement,
+            Err(e) => return Box::new(backend::Misconfigured(e)),
+        };
+        // Tokens are looked up for the registry actually queried.
+        let registry = replacement
+            .as_ref()
+            .map(|replacement| replacement.name.clone())
+            .or_else(|| self.registry.clone());
+        let (kind, base_url) = match (replacement, &self.registry) {
+            (Some(replacement), _) => match replacement.source {
+                ReplacementSource::Registry(index) => (index_kind(&index), Some(index)),
+                ReplacementSource::LocalRegistry(dir) => {
+                    return Box::new(backend::LocalRegistry::new(dir))
+                }
+                ReplacementSource::Directory(dir) => {
+                    return Box::new(backend::VendorDirectory::new(dir))
+                }
+            },
+            (None, Some(name)) => match config.registry_index(name) {
+                Some(index) => (index_kind(&index), Some(index)),
+                None => {
+                    let e = Error::UnknownRegistry(name.clone());
+                    return Box::new(backend::Misconfigured(e));
+                }
+            },
+            (None, None) => (self.backend, self.base_url.clone()),
+        };
+
+        let user_agent = match (&self.user_agent, &self.contact) {
+            (Some(user_agent), _) => user_agent.clone(),
+            (None, Some(contact)) => http::user_agent(Some(contact)),
+            (None, None) => http::default_user_agent(),
+        };
+        let rate_limit = self.rate_limit.unwrap_or(match kind {
+            BackendKind::Api => RateLimit::crates_io(),
+            _ => RateLimit::none(),
+        });
+        // Only send tokens to the registry they belong to. Credential
+        // providers are only run once a registry requires a token.
+        let token_source = match (&self.token, &registry, &base_url) {
+            (Some(token), _, _) => {
+                let token = token.clone();
+                Some(Box::new(move || Ok(Some(token.clone()))) as backend::TokenSource)
+            }
+            (None, Some(name), Some(index)) if kind == BackendKind::SparseIndex => {
+                let (config, name, index) = (config.clone(), name.clone(), index.clone());
+                Some(Box::new(move || credentials::registry_token(&config, &name, &index)) as _)
+            }
+            _ => None,
+        };
+
+        // Explicit settings take precedence over Cargo's configuration.
+        let cargo = config.http();
+        let http = Http::new(HttpSettings {
+            timeout: self
+                .timeout
+                .or(cargo.timeout)
+                .unwrap_or_else(|| Duration::from_secs(DEFAULT_TIMEOUT_SECONDS)),
+            user_agent,
+            retry: self.retry,
+            rate_limit,
+            proxy: self.proxy.clone().or(cargo.proxy),
+            ca_bundle: self.ca_bundle.clone().or(cargo.cainfo),
+        });
+        let (network, source): (Box<dyn Backend>, _) = match kind {
+            BackendKind::Api => {
+                let base_url = base_url.unwrap_or_else(|| backend::API_BASE_URL.to_string());
+                let source = source(kind, &base_url);
+                let api = backend::Api::new(http, base_url).token(self.token.clone());
+                (Box::new(api), source)
+            }
+            BackendKind::SparseIndex => {
+                let base_url = base_url.unwrap_or_else(|| backend::SPARSE_INDEX_URL.to_string());
+                let source = source(kind, &base_url);
+                let index = backend::SparseIndex::new(http, base_url).token_source(token_source);
+                (Box::new(index), source)
+            }
+            BackendKind::GitIndex => {
+                let url = base_url.unwrap_or_else(|| backend::GIT_INDEX_URL.to_string());
+                let dir = backend::default_git_index_dir(&url);
+                return Box::new(backend::GitIndex::new(url, dir));
+            }
+            BackendKind::Offline => {
+                return Box::new(backend::Offline::new(
+                    self.cargo_home.clone().or_else(|| home::cargo_home().ok()),
+                ))
+            }
+            BackendKind::DbDump => {
+                return Box::new(backend::DbDump::new(
+                    self.db_dump_path.clone().or_else(default_db_dump_path),
+                ))
+            }
+            BackendKind::Snapshot => {
+                return Box::new(backend::SnapshotBackend::new(
+                    self.snapshot_path.clone().or_else(default_snapshot_path),
+                ))
+            }
+        };
+
+        match self.cache_ttl {
+            Some(ttl) => Box::new(backend::Cached::new(
+                network,
+                source,
+                self.cache_path.clone().or_else(default_cache_path),
+                ttl,
+                self.refresh_cache,
+            )),
+            None => network,
+        }
+    }
+}
+
+impl Default for CheckerBuilder {
+    fn default() -> Self {
+        Self {
+            backend: BackendKind::default(),
+            base_url: None,
+            registry: None,
+            cargo_home: None,
+            db_dump_path: None,
+            snapshot_path: None,
+            timeout: None,
+            proxy: None,
+            ca_bundle: None,
+            user_agent: None,
+            contact: None,
+            token: None,
+            retry: RetryPolicy::default(),
+            rate_limit: None,
+            reserved: HashSet::new(),
+            jobs: DEFAULT_JOBS,
+            cache_ttl: None,
+            cache_path: None,
+            refresh_cache: false,
+        }
+    }
+}
+
+/// Returns the backend used to query the registry index at `index`.
+fn index_kind(index: &str) -> BackendKind {
+    if index.starts_with("sparse+") {
+        BackendKind::SparseIndex
+    } else {
+        BackendKind::GitIndex
+    }
+}
+
+/// Identifies the registry queried by a network backend within the result
+/// cache.
+fn source(kind: BackendKind, base_url: &str) -> String {
+    let base_url = base_url.strip_prefix("sparse+").unwrap_or(base_url);
+    format!("{:?}:{}", kind, base_url.trim_end_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{Response, Server},
+        InvalidReason, MockBackend,
+    };
+    use std::{env, sync::Arc};
+
+    fn checker(server: &Server) -> CheckerBuilder {
+        Checker::builder()
+            .base_url(format!("{}/", server.url()))
+            .rate_limit(RateLimit::none())
+    }
+
+    #[test]
+    fn taken_and_free_names() {
+        let server = Server::new(vec![(
+            "/api/v1/crates/serde",
+            Response::ok(r#"{"crate":{"name":"serde"}}"#),
+        )]);
+        let checker = checker(&server).build();
+        assert_eq!(
+            checker.check_availability("serde"),
+            Ok(Availability::Unavailable)
+        );
+        assert_eq!(
+            checker.check_availability("free"),
+            Ok(Availability::Available)
+        );
+        assert_eq!(
+            server.paths(),
+            ["/api/v1/crates/serde", "/api/v1/crates/free"]
+        );
+    }
+
+    #[test]
+    fn check_reports_spelling() {
+        let server = Server::new(vec![(
+            "/api/v1/crates/Serde-JSON",
+            Response::ok(r#"{"crate":{"name":"serde_json"}}"#),
+        )]);
+        let check = checker(&server).build().check("Serde-JSON").unwrap();
+        assert_eq!(check.name, "Serde-JSON");
+        assert_eq!(check.canonical, "serde_json");
+        assert_eq!(check.availability, Availability::Unavailable);
+        assert_eq!(check.taken_as.as_deref(), Some("serde_json"));
+    }
+
+    #[test]
+    fn empty_name() {
+        let server = Server::new(vec![]);
+        let checker = checker(&server).build();
+        assert_eq!(checker.check_availability(""), Err(Error::EmptyCrateName));
+        assert!(server.paths().is_empty());
+    }
+
+    #[test]
+    fn invalid_names_are_not_looked_up() {
+        let server = Server::new(vec![]);
+        let checker = checker(&server).build();
+        assert_eq!(
+            checker.check_availability("1foo"),
+            Ok(Availability::Invalid(InvalidReason::InvalidStart('1')))
+        );
+        assert!(server.paths().is_empty());
+    }
+
+    #[test]
+    fn reserved_names_are_not_looked_up() {
+        let server = Server::new(vec![]);
+        let checker = checker(&server).reserved_names(["internal-tool"]).build();
+        assert_eq!(
+            checker.check_availability("std"),
+            Ok(Availability::Reserved)
+        );
+        assert_eq!(
+            checker.check_availability("Internal_Tool"),
+            Ok(Availability::Reserved)
+        );
+        assert!(server.paths().is_empty());
+    }
+
+    #[test]
+    fn invalid_and_reserved_names_are_not_looked_up() {
+        let server = Server::new(vec![]);
+        let checker = checker(&server).reserved_names(["internal-tool"]).build();
+        assert_eq!(
+            checker.lookup("1abc"),
+            Err(Error::InvalidCrateName(InvalidReason::InvalidStart('1')))
+        );
+        assert_eq!(
+            checker.lookup("foo bar"),
+            Err(Error::InvalidCrateName(InvalidReason::InvalidCharacter(
+                ' '
+            )))
+        );
+        assert_eq!(checker.lookup("std"), Err(Error::ReservedCrateName));
+        assert_eq!(
+            checker.lookup("Internal_Tool"),
+            Err(Error::ReservedCrateName)
+        );
+        assert!(server.paths().is_empty());
+    }
+
+    #[test]
+    fn crates_io_reservations_only_apply_to_crates_io() {
+        let checker = Checker::builder()
+            .registry("my-registry")
+            .reserved_names(["internal-tool"])
+            .build_with(MockBackend::new());
+        assert_eq!(
+            checker.check_availability("std"),
+            Ok(Availability::Available)
+        );
+        assert_eq!(
+            checker.check_availability("internal-tool"),
+            Ok(Availability::Reserved)
+        );
+    }
+
+    #[test]
+    fn crates_io_registry() {
+        let checker = Checker::builder()
+            .registry("crates-io")
+            .build_with(MockBackend::new());
+        assert_eq!(
+            checker.check_availability("std"),
+            Ok(Availability::Reserved)
+        );
+    }
+
+    #[test]
+    fn registry_token() {
+        let server = Server::new(vec![
+            (
+                "/config.json",
+                Response::ok(r#"{"dl":"x","auth-required":true}"#),
+            ),
+            ("/3/f/foo", Response::status(404)),
+        ]);
+        env::set_var(
+            "CARGO_REGISTRIES_CARGO_FREE_TEST_PRIVATE_INDEX",
+            format!("sparse+{}/", server.url()),
+        );
+        env::set_var("CARGO_REGISTRIES_CARGO_FREE_TEST_PRIVATE_TOKEN", "secret");
+        let checker = Checker::builder()
+            .registry("cargo-free-test-private")
+            .build();
+        assert_eq!(
+            checker.check_availability("foo"),
+            Ok(Availability::Available)
+        );
+        assert_eq!(server.requests()[1].headers["authorization"], "secret");
+    }
+
+    #[test]
+    fn unknown_registry() {
+        let checker = Checker::builder()
+            .registry("cargo-free-test-unknown")
+            .build();
+        assert_eq!(
+            checker.check_availability("foo"),
+            Err(Error::UnknownRegistry(
+                "cargo-free-test-unknown".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn cache_sources() {
+        assert_eq!(
+            source(BackendKind::Api, "https://crates.io/"),
+            "Api:https://crates.io"
+        );
+        assert_eq!(
+            source(BackendKind::SparseIndex, "sparse+https://index.crates.io/"),
+            "SparseIndex:https://index.crates.io"
+        );
+    }
+
+    #[test]
+    fn sends_user_agent() {
+        let server = Server::new(vec![]);
+        let checker = checker(&server).user_agent("test-agent").build();
+        checker.check_availability("foo").unwrap();
+        assert_eq!(server.requests()[0].headers["user-agent"], "test-agent");
+    }
+
+    #[test]
+    fn sends_contact_in_default_user_agent() {
+        let server = Server::new(vec![]);
+        let checker = checker(&server).contact("me@example.com").build();
+        checker.check_availability("foo").unwrap();
+        let user_agent = &server.requests()[0].headers["user-agent"];
+        assert!(user_agent.starts_with("cargo-free/"));
+        assert!(user_agent.ends_with("; me@example.com)"));
+    }
+
+    #[test]
+    fn retries_server_errors() {
+        let server = Server::new(vec![
+            ("/api/v1/crates/foo", Response::status(503)),
+            (
+                "/api/v1/crates/foo",
+                Response::ok(r#"{"crate":{"name":"serde"}}"#),
+            ),
+        ]);
+        let checker = checker(&server)
+            .retry_policy(RetryPolicy::new(1, Duration::from_millis(1)))
+            .build();
+        assert_eq!(
+            checker.check_availability("foo"),
+            Ok(Availability::Unavailable)
+        );
+        assert_eq!(server.paths().len(), 2);
+    }
+
+    #[test]
+    fn no_retries_by_default() {
+        let server = Server::new(vec![("/api/v1/crates/foo", Response::status(503))]);
+        let checker = checker(&server).build();
+        assert_eq!(
+            checker.check_availability("foo"),
+            Err(Error::ServerError(503))
+        );
+        assert_eq!(server.paths().len(), 1);
+    }
+
+    #[test]
+    fn rate_limited() {
+        let server = Server::new(vec![(
+            "/api/v1/crates/foo",
+            Response::status(429).header("Retry-After", "30"),
+        )]);
+        let checker = checker(&server).build();
+        assert_eq!(
+            checker.check_availability("foo"),
+            Err(Error::RateLimited {
+                retry_after: Some(Duration::from_secs(30))
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_response() {
+        let server = Server::new(vec![
+            ("/api/v1/crates/foo", Response::ok("not json")),
+            ("/api/v1/crates/bar", Response::ok(r#"{"crate":{}}"#)),
+        ]);
+        let checker = checker(&server).build();
+        assert!(matches!(
+            checker.check_availability("foo"),
+            Err(Error::InvalidResponse(_))
+        ));
+        assert!(matches!(
+            checker.check_availability("bar"),
+            Err(Error::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn transport_error() {
+        // Nothing listens on port 1.
+        let checker = Checker::builder().base_url("http://127.0.0.1:1").build();
+        let error = checker.check_availability("foo").unwrap_err();
+        assert!(matches!(error, Error::Transport(_)));
+        assert!(error.is_transient());
+    }
+
+    #[test]
+    fn check_many_looks_up_canonical_names_once() {
+        let server = Server::new(vec![(
+            "/api/v1/crates/foo-bar",
+            Response::ok(r#"{"crate":{"name":"foo_bar"}}"#),
+        )]);
+        let checker = checker(&server).jobs(2).build();
+        let results = checker.check_many(["foo-bar", "baz", "Foo_Bar", "1foo", "qux"]);
+
+        let checks = results
+            .into_iter()
+            .map(Result::unwrap)
+            .map(|check| (check.name, check.availability))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            checks,
+            [
+                ("foo-bar".to_string(), Availability::Unavailable),
+                ("baz".to_string(), Availability::Available),
+                ("Foo_Bar".to_string(), Availability::Unavailable),
+                (
+                    "1foo".to_string(),
+                    Availability::Invalid(InvalidReason::InvalidStart('1'))
+                ),
+                ("qux".to_string(), Availability::Available),
+            ]
+        );
+        let mut paths = server.paths();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "/api/v1/crates/baz",
+                "/api/v1/crates/foo-bar",
+                "/api/v1/crates/qux"
+            ]
+        );
+    }
+
+    #[test]
+    fn check_many_reports_failures() {
+        let server = Server::new(vec![("/api/v1/crates/foo", Response::status(503))]);
+        let checker = checker(&server).build();
+        let results = checker.check_many(["foo", "bar"]);
+        assert_eq!(results[0], Err(Error::ServerError(503)));
+        assert_eq!(
+            results[1].as_ref().unwrap().availability,
+            Availability::Available
+        );
+    }
+
+    #[test]
+    fn check_many_without_names() {
+        let server = Server::new(vec![]);
+        assert!(checker(&server)
+            .build()
+            .check_many(Vec::<String>::new())
+            .is_empty());
+    }
+
+    #[test]
+    fn lookup_from_api() {
+        let server = Server::new(vec![
+            (
+                "/api/v1/crates/Serde",
+                Response::ok(
+                    r#"{
+                        "crate": {
+                            "name": "serde",
+                            "description": " A serialization framework\n",
+                            "repository": "https://github.com/serde-rs/serde",
+                            "created_at": "2014-12-05T20:20:39.487502+00:00",
+                            "updated_at": "2024-01-01T00:00:00+00:00",
+                            "max_version": "1.0.0",
+                            "downloads": 100,
+                            "recent_downloads": 10
+                        },
+                        "versions": [{"yanked": false}, {"yanked": true}]
+                    }"#,
+                ),
+            ),
+            (
+                "/api/v1/crates/serde/owners",
+                Response::ok(
+                    r#"{"users":[{"login":"dtolnay"},{"login":"github:serde-rs:publish"}]}"#,
+                ),
+            ),
+        ]);
+        let info = checker(&server).build().lookup("Serde").unwrap().unwrap();
+        assert_eq!(info.name, "serde");
+        assert_eq!(
+            info.owners,
+            Some(vec![
+                "dtolnay".to_string(),
+                "github:serde-rs:publish".to_string()
+            ])
+        );
+        assert_eq!(
+            info.description.as_deref(),
+            Some("A serialization framework")
+        );
+        assert_eq!(
+            info.repository.as_deref(),
+            Some("https://github.com/serde-rs/serde")
+        );
+        assert!(info.created_at.unwrap() < info.updated_at.unwrap());
+        assert_eq!(info.max_version.as_deref(), Some("1.0.0"));
+        assert_eq!(
+            (info.downloads, info.recent_downloads),
+            (Some(100), Some(10))
+        );
+        assert_eq!(info.yanked, Some(false));
+    }
+
+    #[test]
+    fn lookup_from_api_without_owners() {
+        let server = Server::new(vec![(
+            "/api/v1/crates/foo",
+            Response::ok(
+                r#"{"crate":{"name":"foo","max_version":"0.0.0"},"versions":[{"yanked":true}]}"#,
+            ),
+        )]);
+        let info = checker(&server).build().lookup("foo").unwrap().unwrap();
+        assert_eq!(info.owners, None);
+        assert_eq!(info.max_version, None);
+        assert_eq!(info.yanked, Some(true));
+        assert_eq!(checker(&server).build().lookup("bar"), Ok(None));
+    }
+
+    #[test]
+    fn lookup_from_lookups() {
+        let summary = CrateSummary {
+            created_at: None,
+            downloads: Some(5),
+        };
+        let checker = Checker::builder().build_with(
+            MockBackend::new()
+                .taken_with_summary("serde", summary)
+                .lookup("foo", Status::Unknown.into()),
+        );
+        let info = checker.lookup("serde").unwrap().unwrap();
+        assert_eq!((info.name.as_str(), info.downloads), ("serde", Some(5)));
+        assert_eq!(checker.lookup("bar"), Ok(None));
+        assert_eq!(checker.lookup("foo"), Err(Error::Unresolved));
+        assert_eq!(checker.lookup(""), Err(Error::EmptyCrateName));
+    }
+
+    #[test]
+    fn squat_report_without_package() {
+        let mut info = CrateInfo::new("foo");
+        info.max_version = Some("0.0.1".to_string());
+        let checker = Checker::builder().build_with(MockBackend::new());
+        assert_eq!(checker.squat_report(&info), Ok(info.squat_report()));
+    }
+
+    #[test]
+    fn squat_report_with_failed_download() {
+        let server = Server::new(vec![(
+            "/api/v1/crates/foo/0.0.1/download",
+            Response::status(404),
+        )]);
+        let mut info = CrateInfo::new("foo");
+        info.max_version = Some("0.0.1".to_string());
+        assert!(matches!(
+            checker(&server).build().squat_report(&info),
+            Err(Error::InvalidResponse(_))
+        ));
+        assert_eq!(server.paths(), ["/api/v1/crates/foo/0.0.1/download"]);
+    }
+
+    #[test]
+    fn lookup_dormancy_from_api() {
+        let server = Server::new(vec![(
+            "/api/v1/crates/foo",
+            Response::ok(
+                r#"{
+                    "crate": {
+                        "name": "foo",
+                        "badges": [
+                            {"badge_type": "travis-ci", "attributes": {}},
+                            {"badge_type": "maintenance", "attributes": {"status": "deprecated"}}
+                        ]
+                    },
+                    "versions": [
+                        {"created_at": "2019-01-01T00:00:00+00:00"},
+                        {"created_at": "2020-01-01T00:00:00+00:00"}
+                    ]
+                }"#,
+            ),
+        )]);
+        let info = checker(&server).build().lookup("foo").unwrap().unwrap();
+        assert_eq!(
+            info.published_at,
+            crate::dump::parse_timestamp("2020-01-01T00:00:00+00:00")
+        );
+        assert_eq!(info.maintenance.as_deref(), Some("deprecated"));
+        assert!(info.dormancy().is_dormant());
+    }
+
+    #[test]
+    fn check_looks_up_skeleton() {
+        let backend = Arc::new(MockBackend::new().taken("serde"));
+        let checker = Checker::builder().build_with(Arc::clone(&backend));
+        let check = checker.check("s\u{0435}rde").unwrap();
+        assert_eq!(
+            check.availability,
+            Availability::Invalid(InvalidReason::InvalidCharacter('\u{0435}'))
+        );
+        assert_eq!(check.confusables.len(), 1);
+        let skeleton = check.skeleton.unwrap().unwrap();
+        assert_eq!(skeleton.name, "serde");
+        assert_eq!(skeleton.availability, Availability::Unavailable);
+        assert_eq!(backend.lookups(), ["serde"]);
+
+        // Only `check` looks up skeletons.
+        checker.check_availability("s\u{0435}rde").unwrap();
+        assert_eq!(backend.lookups().len(), 1);
+    }
+
+    #[test]
+    fn check_without_skeleton() {
+        let checker = Checker::builder().build_with(MockBackend::new());
+        let check = checker.check("s\u{65e5}rde").unwrap();
+        assert!(check.confusables.is_empty());
+        assert_eq!(check.skeleton, None);
+        assert_eq!(checker.check("serde").unwrap().skeleton, None);
+    }
+
+    #[test]
+    fn check_many_looks_up_skeletons() {
+        let backend = Arc::new(MockBackend::new());
+        let checker = Checker::builder().build_with(Arc::clone(&backend));
+        let results = checker.check_many(["s\u{0435}rde", "serde"]);
+        let skeleton = results[0].as_ref().unwrap().skeleton.as_deref().unwrap();
+        assert_eq!(
+            skeleton.as_ref().unwrap().availability,
+            Availability::Available
+        );
+        assert_eq!(results[1].as_ref().unwrap().skeleton, None);
+        // The skeleton shares the lookup of the ASCII name.
+        assert_eq!(backend.lookups(), ["serde"]);
+    }
+
+    #[test]
+    fn failed_skeleton_lookups_keep_the_check() {
+        let checker =
+            Checker::builder().build_with(MockBackend::new().failing_all(Error::ServerError(503)));
+        for check in [
+            checker.check("s\u{0435}rde").unwrap(),
+            checker.check_many(["s\u{0435}rde"]).remove(0).unwrap(),
+        ] {
+            assert_eq!(
+                check.availability,
+                Availability::Invalid(InvalidReason::InvalidCharacter('\u{0435}'))
+            );
+            assert_eq!(check.confusables.len(), 1);
+            assert_eq!(check.skeleton, Some(Box::new(Err(Error::ServerError(503)))));
+        }
+    }
+
+    #[test]
+    fn check_many_validates_each_spelling() {
+        let backend = Arc::new(MockBackend::new());
+        let checker = Checker::builder()
+            .reserved_names(["internal-tool"])
+            .build_with(Arc::clone(&backend));
+        let results = checker.check_many(["-foo", "_foo", "foo", "Internal_Tool"]);
+        let availability = |i: usize| results[i].as_ref().unwrap().availability;
+        assert_eq!(
+            availability(0),
+            Availability::Invalid(InvalidReason::InvalidStart('-'))
+        );
+        assert_eq!(
+            availability(1),
+            Availability::Invalid(InvalidReason::InvalidStart('_'))
+        );
+        assert_eq!(availability(2), Availability::Available);
+        assert_eq!(availability(3), Availability::Reserved);
+        assert_eq!(backend.lookups(), ["foo"]);
+    }
+}
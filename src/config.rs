@@ -0,0 +1,430 @@
+//! Reads the parts of Cargo's configuration cargo-free honors.
+//!
+//! Like Cargo, settings are looked up in the environment (e.g.
+//! `CARGO_NET_RETRY` for `net.retry`) first, then in the `.cargo/config.toml`
+//! files of the working directory and its ancestors, and finally in
+//! `$CARGO_HOME/config.toml`. Registry tokens are also read from
+//! `$CARGO_HOME/credentials.toml`.
+
+use crate::Error;
+use std::{
+    convert::TryFrom,
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use toml::Value;
+
+/// The merged Cargo configuration files, ordered by precedence.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CargoConfig {
+    files: Vec<(PathBuf, Value)>,
+}
+
+impl CargoConfig {
+    /// Loads the configuration that applies to the current working directory.
+    /// Unreadable or malformed files are skipped with a warning.
+    pub(crate) fn load() -> Self {
+        let cwd = env::current_dir().unwrap_or_default();
+        Self::load_from(&cwd, home::cargo_home().ok().as_deref())
+    }
+
+    /// Loads the configuration that applies to `cwd`.
+    pub(crate) fn load_from(cwd: &Path, cargo_home: Option<&Path>) -> Self {
+        let mut dirs = cwd
+            .ancestors()
+            .map(|dir| dir.join(".cargo"))
+            .collect::<Vec<_>>();
+        if let Some(cargo_home) = cargo_home {
+            if !dirs.iter().any(|dir| dir == cargo_home) {
+                dirs.push(cargo_home.to_path_buf());
+            }
+        }
+
+        // Tokens stored by `cargo login` take precedence over the ones in
+        // configuration files.
+        let credentials = cargo_home.and_then(|dir| read_file(dir, "credentials"));
+        let files = credentials
+            .into_iter()
+            .chain(dirs.iter().filter_map(|dir| read_file(dir, "config")))
+            .collect();
+        Self { files }
+    }
+
+    /// Returns the value of the dotted `key`, e.g. `net.retry`, along with
+    /// the file defining it. The file is `None` for values taken from the
+    /// environment.
+    pub(crate) fn get(&self, key: &str) -> Option<(Value, Option<&Path>)> {
+        let var = format!("CARGO_{}", key.to_uppercase().replace(['.', '-'], "_"));
+        if let Ok(value) = env::var(var) {
+            // Environment variables are untyped, parse them like a TOML value
+            // and fall back to a string.
+            let value = format!("v = {}", value)
+                .parse::<Value>()
+                .ok()
+                .and_then(|table| table.get("v").cloned())
+                .unwrap_or(Value::String(value));
+            return Some((value, None));
+        }
+
+        self.files.iter().find_map(|(path, value)| {
+            let value = key
+                .split('.')
+                .try_fold(value, |value, segment| value.get(segment))?;
+            Some((value.clone(), Some(path.as_path())))
+        })
+    }
+
+    /// Returns the integer value of `key`.
+    pub(crate) fn integer(&self, key: &str) -> Option<i64> {
+        self.get(key)?.0.as_integer()
+    }
+
+    /// Returns the string value of `key`.
+    pub(crate) fn string(&self, key: &str) -> Option<String> {
+        self.get(key)?.0.as_str().map(str::to_string)
+    }
+
+    /// Returns the path value of `key`. Like Cargo, relative paths are
+    /// resolved against the directory containing the `.cargo` directory of
+    /// the defining file, or the working directory for environment
+    /// variables.
+    pub(crate) fn path(&self, key: &str) -> Option<PathBuf> {
+        let (value, file) = self.get(key)?;
+        let path = PathBuf::from(value.as_str()?);
+        let base = match file {
+            Some(file) => file.parent()?.parent()?.to_path_buf(),
+            None => env::current_dir().ok()?,
+        };
+        Some(base.join(path))
+    }
+
+    /// Returns the index URL of the registry `name`, as configured in the
+    /// `[registries]` table.
+    pub(crate) fn registry_index(&self, name: &str) -> Option<String> {
+        self.string(&format!("registries.{}.index", name))
+    }
+
+    /// Returns the source replacing the registry `name` (`crates-io` for
+    /// crates.io), following the chain of `source.<name>.replace-with`
+    /// entries. The replacement may name a `[source]` or a `[registries]`
+    /// entry.
+    pub(crate) fn source_replacement(
+        &self,
+        name: &str,
+    ) -> Result<Option<SourceReplacement>, Error> {
+        let mut chain = vec![name.to_string()];
+        while let Some(next) = chain
+            .last()
+            .and_then(|last| self.string(&format!("source.{}.replace-with", last)))
+        {
+            if chain.contains(&next) {
+                return Err(Error::SourceReplacement(format!(
+                    "`{}` is replaced by itself",
+                    next
+                )));
+            }
+            chain.push(next);
+        }
+
+        let replacement = match chain.pop().filter(|_| !chain.is_empty()) {
+            Some(replacement) => replacement,
+            None => return Ok(None),
+        };
+        let key = |field: &str| format!("source.{}.{}", replacement, field);
+        let source = if let Some(index) = self.string(&key("registry")) {
+            ReplacementSource::Registry(index)
+        } else if let Some(dir) = self.path(&key("local-registry")) {
+            ReplacementSource::LocalRegistry(dir)
+        } else if let Some(dir) = self.path(&key("directory")) {
+            ReplacementSource::Directory(dir)
+        } else if let Some(index) = self.registry_index(&replacement) {
+            ReplacementSource::Registry(index)
+        } else {
+            return Err(Error::SourceReplacement(format!(
+                "`{}` is not a registry, local registry or directory source",
+                replacement
+            )));
+        };
+
+        Ok(Some(SourceReplacement {
+            name: replacement,
+            source,
+        }))
+    }
+
+    /// Returns the settings of the `[http]` table.
+    pub(crate) fn http(&self) -> HttpConfig {
+        HttpConfig {
+            proxy: self.string("http.proxy").filter(|proxy| !proxy.is_empty()),
+            cainfo: self.path("http.cainfo"),
+            timeout: self
+                .integer("http.timeout")
+                .and_then(|secs| u64::try_from(secs).ok())
+                .map(Duration::from_secs),
+        }
+    }
+}
+
+/// Reads the file `name` or `name.toml` in `dir`. Like Cargo, the file
+/// without extension is preferred if both exist.
+fn read_file(dir: &Path, name: &str) -> Option<(PathBuf, Value)> {
+    let path = [name.to_string(), format!("{}.toml", name)]
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())?;
+    let value = fs::read_to_string(&path).ok()?.parse::<Value>();
+    match value {
+        Ok(value) => Some((path, value)),
+        Err(e) => {
+            log::warn!("ignoring {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// The settings of Cargo's `[http]` table cargo-free honors.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct HttpConfig {
+    /// The proxy to use for all requests (`http.proxy`).
+    pub(crate) proxy: Option<String>,
+
+    /// The CA bundle used to verify TLS certificates (`http.cainfo`).
+    pub(crate) cainfo: Option<PathBuf>,
+
+    /// The timeout for each request (`http.timeout`).
+    pub(crate) timeout: Option<Duration>,
+}
+
+/// A source configured to replace a registry, e.g. a crates.io mirror.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct SourceReplacement {
+    /// The name of the replacing source.
+    pub(crate) name: String,
+
+    /// Where the replacing source is located.
+    pub(crate) source: ReplacementSource,
+}
+
+/// The kinds of replacement sources cargo-free can query.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ReplacementSource {
+    /// A registry index, served via the sparse (`sparse+https://...`) or git
+    /// protocol.
+    Registry(String),
+
+    /// A local registry, i.e. a directory containing an `index` directory.
+    LocalRegistry(PathBuf),
+
+    /// A directory of vendored crates, e.g. created by `cargo vendor`.
+    Directory(PathBuf),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn nearest_file_wins() {
+        let root = temp_dir("config");
+        let project = root.join("project");
+        let cargo_home = root.join("cargo-home");
+        for dir in [
+            root.join(".cargo"),
+            project.join(".cargo"),
+            cargo_home.clone(),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(
+            root.join(".cargo/config.toml"),
+            "[cargo-free-test]\nouter = 1\ninner = 1",
+        )
+        .unwrap();
+        fs::write(
+            project.join(".cargo/config.toml"),
+            "[cargo-free-test]\ninner = 2",
+        )
+        .unwrap();
+        // `config` takes precedence over `config.toml` in the same directory.
+        fs::write(
+            project.join(".cargo/config"),
+            "[cargo-free-test]\ninner = 3",
+        )
+        .unwrap();
+        fs::write(
+            cargo_home.join("config.toml"),
+            "[cargo-free-test]\nhome = 4",
+        )
+        .unwrap();
+
+        let config = CargoConfig::load_from(&project, Some(&cargo_home));
+        assert_eq!(config.integer("cargo-free-test.inner"), Some(3));
+        assert_eq!(config.integer("cargo-free-test.outer"), Some(1));
+        assert_eq!(config.integer("cargo-free-test.home"), Some(4));
+        assert_eq!(config.integer("cargo-free-test.missing"), None);
+        let (_, path) = config.get("cargo-free-test.outer").unwrap();
+        assert_eq!(path, Some(root.join(".cargo/config.toml").as_path()));
+    }
+
+    #[test]
+    fn malformed_files_are_skipped() {
+        let root = temp_dir("config-malformed");
+        fs::create_dir_all(root.join(".cargo")).unwrap();
+        fs::write(root.join(".cargo/config.toml"), "[net\nretry = 1").unwrap();
+        let config = CargoConfig::load_from(&root, None);
+        assert_eq!(config.integer("cargo-free-test.retry"), None);
+    }
+
+    #[test]
+    fn environment_overrides_files() {
+        env::set_var("CARGO_CARGO_FREE_TEST_ENV_VALUE", "5");
+        env::set_var("CARGO_CARGO_FREE_TEST_ENV_NAME", "not toml");
+        let config = CargoConfig::default();
+        assert_eq!(config.integer("cargo-free-test.env-value"), Some(5));
+        assert_eq!(
+            config.get("cargo-free-test.env-name"),
+            Some((Value::String("not toml".to_string()), None))
+        );
+    }
+
+    #[test]
+    fn http_settings() {
+        let root = temp_dir("config-http");
+        let project = root.join("project");
+        fs::create_dir_all(project.join(".cargo")).unwrap();
+        fs::write(
+            project.join(".cargo/config.toml"),
+            "[http]\nproxy = \"http://proxy:3128\"\ncainfo = \"certs/ca.pem\"\ntimeout = 20",
+        )
+        .unwrap();
+        let config = CargoConfig::load_from(&project, None);
+        assert_eq!(
+            config.http(),
+            HttpConfig {
+                proxy: Some("http://proxy:3128".to_string()),
+                cainfo: Some(project.join("certs/ca.pem")),
+                timeout: Some(Duration::from_secs(20)),
+            }
+        );
+    }
+
+    #[test]
+    fn empty_proxy_is_ignored() {
+        let root = temp_dir("config-http-empty");
+        fs::create_dir_all(root.join(".cargo")).unwrap();
+        fs::write(
+            root.join(".cargo/config.toml"),
+            "[http]\nproxy = \"\"\ntimeout = -1",
+        )
+        .unwrap();
+        assert_eq!(
+            CargoConfig::load_from(&root, None).http(),
+            HttpConfig::default()
+        );
+    }
+
+    #[test]
+    fn registry_index() {
+        let root = temp_dir("config-registries");
+        fs::create_dir_all(root.join(".cargo")).unwrap();
+        fs::write(
+            root.join(".cargo/config.toml"),
+            "[registries.my-registry]\nindex = \"sparse+https://example.com/index/\"",
+        )
+        .unwrap();
+        let config = CargoConfig::load_from(&root, None);
+        assert_eq!(
+            config.registry_index("my-registry").as_deref(),
+            Some("sparse+https://example.com/index/")
+        );
+        assert_eq!(config.registry_index("other"), None);
+    }
+
+    fn config(toml: &str) -> CargoConfig {
+        CargoConfig {
+            files: vec![(
+                PathBuf::from("/project/.cargo/config.toml"),
+                toml.parse().unwrap(),
+            )],
+        }
+    }
+
+    #[test]
+    fn no_replacement() {
+        assert_eq!(config("").source_replacement("crates-io").unwrap(), None);
+    }
+
+    #[test]
+    fn replacement_chain() {
+        let config = config(
+            r#"
+            [source.crates-io]
+            replace-with = "mirror"
+            [source.mirror]
+            replace-with = "vendored"
+            [source.vendored]
+            directory = "vendor"
+            "#,
+        );
+        assert_eq!(
+            config.source_replacement("crates-io").unwrap(),
+            Some(SourceReplacement {
+                name: "vendored".to_string(),
+                source: ReplacementSource::Directory(PathBuf::from("/project/vendor")),
+            })
+        );
+    }
+
+    #[test]
+    fn replacement_by_registry() {
+        let config = config(
+            r#"
+            [source.crates-io]
+            replace-with = "company"
+            [registries.company]
+            index = "sparse+https://index.example.com/"
+            "#,
+        );
+        assert_eq!(
+            config.source_replacement("crates-io").unwrap(),
+            Some(SourceReplacement {
+                name: "company".to_string(),
+                source: ReplacementSource::Registry(
+                    "sparse+https://index.example.com/".to_string()
+                ),
+            })
+        );
+    }
+
+    #[test]
+    fn replacement_cycle() {
+        let config = config(
+            r#"
+            [source.crates-io]
+            replace-with = "a"
+            [source.a]
+            replace-with = "crates-io"
+            "#,
+        );
+        assert!(matches!(
+            config.source_replacement("crates-io"),
+            Err(Error::SourceReplacement(_))
+        ));
+    }
+
+    #[test]
+    fn replacement_unknown_source() {
+        let config = config(
+            r#"
+            [source.crates-io]
+            replace-with = "missing"
+            "#,
+        );
+        assert!(matches!(
+            config.source_replacement("crates-io"),
+            Err(Error::SourceReplacement(_))
+        ));
+    }
+}
@@ -0,0 +1,151 @@
+//! Detection of non-ASCII characters resembling ASCII ones, following the
+//! confusable detection of Unicode Technical Standard #39.
+//!
+//! Names pasted from chats or documents may contain e.g. a Cyrillic `а` or
+//! fullwidth letters. crates.io rejects such names, but the ASCII name they
+//! resemble, their skeleton, may be available.
+
+use unicode_normalization::UnicodeNormalization;
+
+/// A non-ASCII character resembling ASCII text, see [`confusables`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Confusable {
+    /// The position of the character in the name, counted in characters.
+    pub position: usize,
+
+    /// The confusable character, e.g. a Cyrillic `а`.
+    pub character: char,
+
+    /// The ASCII text the character resembles, e.g. `a`.
+    pub ascii: String,
+}
+
+/// Returns the non-ASCII characters of `name` that resemble ASCII text.
+///
+/// ```
+/// use cargo_free::confusables;
+///
+/// let confusables = confusables("s\u{0435}rde");
+/// assert_eq!(confusables.len(), 1);
+/// assert_eq!(confusables[0].position, 1);
+/// assert_eq!(confusables[0].ascii, "e");
+/// ```
+pub fn confusables(name: impl AsRef<str>) -> Vec<Confusable> {
+    name.as_ref()
+        .chars()
+        .enumerate()
+        .filter(|(_, c)| !c.is_ascii())
+        .filter_map(|(position, character)| {
+            Some(Confusable {
+                position,
+                character,
+                ascii: ascii_prototype(character)?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the ASCII name `name` resembles, by replacing all non-ASCII
+/// characters with the ASCII text they resemble.
+///
+/// # Returns
+///
+/// `None` if `name` is plain ASCII, or contains a non-ASCII character that
+/// does not resemble ASCII text, e.g. `日`.
+///
+/// ```
+/// use cargo_free::ascii_skeleton;
+///
+/// assert_eq!(ascii_skeleton("\u{0455}erde").as_deref(), Some("serde"));
+/// assert_eq!(ascii_skeleton("\u{ff53}\u{ff45}\u{ff52}\u{ff44}\u{ff45}").as_deref(), Some("serde"));
+/// assert_eq!(ascii_skeleton("serde"), None);
+/// ```
+pub fn ascii_skeleton(name: impl AsRef<str>) -> Option<String> {
+    let name = name.as_ref();
+    if name.is_ascii() {
+        return None;
+    }
+
+    name.chars()
+        .map(|c| {
+            if c.is_ascii() {
+                Some(c.to_string())
+            } else {
+                ascii_prototype(c)
+            }
+        })
+        .collect()
+}
+
+/// Returns the ASCII text the non-ASCII character `c` resembles.
+///
+/// Compatibility characters, e.g. fullwidth letters, are normalized first.
+/// The remaining characters are mapped to their prototype as listed in
+/// `confusables.txt`. Only non-ASCII characters are mapped, as the
+/// prototypes of some ASCII characters differ from them, e.g. `m` is mapped
+/// to `rn`.
+fn ascii_prototype(c: char) -> Option<String> {
+    let prototype = c
+        .to_string()
+        .nfkc()
+        .map(|c| {
+            if c.is_ascii() {
+                c.to_string()
+            } else {
+                unicode_security::skeleton(&c.to_string()).collect()
+            }
+        })
+        .collect::<String>();
+    Some(prototype).filter(|prototype| !prototype.is_empty() && prototype.is_ascii())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skeleton_of_ascii_name() {
+        assert_eq!(ascii_skeleton("serde-json"), None);
+    }
+
+    #[test]
+    fn skeleton_of_lookalikes() {
+        assert_eq!(
+            ascii_skeleton("s\u{0435}rd\u{0435}").as_deref(),
+            Some("serde")
+        );
+        assert_eq!(ascii_skeleton("\u{ff54}okio").as_deref(), Some("tokio"));
+    }
+
+    #[test]
+    fn skeleton_keeps_ascii_characters() {
+        // `m` resembles `rn`, but ASCII characters are left as they are.
+        assert_eq!(ascii_skeleton("m\u{0430}p").as_deref(), Some("map"));
+    }
+
+    #[test]
+    fn no_skeleton_for_unrelated_characters() {
+        assert_eq!(ascii_skeleton("s\u{65e5}rde"), None);
+    }
+
+    #[test]
+    fn confusable_positions() {
+        let confusables = confusables("\u{0455}\u{65e5}\u{0430}");
+        assert_eq!(
+            confusables,
+            [
+                Confusable {
+                    position: 0,
+                    character: '\u{0455}',
+                    ascii: "s".to_string(),
+                },
+                Confusable {
+                    position: 2,
+                    character: '\u{0430}',
+                    ascii: "a".to_string(),
+                },
+            ]
+        );
+    }
+}
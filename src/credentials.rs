@@ -0,0 +1,373 @@
+//! Obtains the tokens of alternative registries like Cargo does, see
+//! <https://doc.rust-lang.org/cargo/reference/registry-authentication.html>.
+//!
+//! The credential providers configured for a registry are asked in order of
+//! precedence. The built-in `cargo:token` provider reads the token from
+//! `CARGO_REGISTRIES_<NAME>_TOKEN` or `registries.<name>.token` in
+//! `credentials.toml` or the configuration files, `cargo:token-from-stdout`
+//! runs a command printing the token. External providers are executed using
+//! Cargo's JSON based credential provider protocol. The platform specific
+//! built-in providers (e.g. `cargo:libsecret`) are not supported.
+
+use crate::{config::CargoConfig, Error};
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+};
+use toml::Value as TomlValue;
+
+/// The providers used if none are configured.
+const DEFAULT_PROVIDER: &str = "cargo:token";
+
+/// Returns the token for the registry `name` with the given index URL, or
+/// `None` if no provider knows one.
+pub(crate) fn registry_token(
+    config: &CargoConfig,
+    name: &str,
+    index_url: &str,
+) -> Result<Option<String>, Error> {
+    for provider in providers(config, name) {
+        let token = match provider.first().map(String::as_str) {
+            Some("cargo:token") => config.string(&format!("registries.{}.token", name)),
+            Some("cargo:token-from-stdout") => token_from_stdout(&provider[1..], name, index_url)?,
+            Some(command) if command.starts_with("cargo:") => {
+                log::warn!("credential provider `{}` is not supported", command);
+                None
+            }
+            Some(_) => run_provider(&provider, name, index_url)?,
+            None => None,
+        };
+        if let Some(token) = token.filter(|token| !token.is_empty()) {
+            return Ok(Some(token));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the credential providers of the registry `name`, highest
+/// precedence first, with aliases resolved.
+fn providers(config: &CargoConfig, name: &str) -> Vec<Vec<String>> {
+    let providers = match config.get(&format!("registries.{}.credential-provider", name)) {
+        Some((provider, _)) => vec![provider],
+        None => match config.get("registry.global-credential-providers") {
+            // Later entries take precedence.
+            Some((TomlValue::Array(providers), _)) => providers.into_iter().rev().collect(),
+            _ => vec![TomlValue::String(DEFAULT_PROVIDER.to_string())],
+        },
+    };
+
+    providers
+        .iter()
+        .map(|provider| {
+            let mut provider = command(provider);
+            let alias = provider
+                .first()
+                .and_then(|first| config.get(&format!("credential-alias.{}", first)));
+            if let Some((alias, _)) = alias {
+                provider.splice(..1, command(&alias));
+            }
+            provider
+        })
+        .collect()
+}
+
+/// Splits a provider given as string or array into its command and
+/// arguments.
+fn command(value: &TomlValue) -> Vec<String> {
+    match value {
+        TomlValue::String(command) => command.split_whitespace().map(str::to_string).collect(),
+        TomlValue::Array(command) => command
+            .iter()
+            .filter_map(|arg| arg.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Runs `command`, which prints the token on stdout.
+fn token_from_stdout(
+    command: &[String],
+    name: &str,
+    index_url: &str,
+) -> Result<Option<String>, Error> {
+    let program = match command.first() {
+        Some(program) => program,
+        None => return Ok(None),
+    };
+    let output = Command::new(program)
+        .args(&command[1..])
+        .env("CARGO_REGISTRY_INDEX_URL", index_url)
+        .env("CARGO_REGISTRY_NAME_OPT", name)
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| credential_error(name, e))?;
+    if !output.status.success() {
+        return Err(credential_error(
+            name,
+            format!("`{}` failed with {}", program, output.status),
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().map(|line| line.trim().to_string()))
+}
+
+/// Asks the external provider `command` for a token using the credential
+/// provider protocol.
+fn run_provider(command: &[String], name: &str, index_url: &str) -> Result<Option<String>, Error> {
+    let mut child = Command::new(&command[0])
+        .args(&command[1..])
+        .arg("--cargo-plugin")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| credential_error(name, e))?;
+    let mut stdin = child.stdin.take().expect("piped stdin");
+    let mut stdout = BufReader::new(child.stdout.take().expect("piped stdout"));
+
+    let mut read = || -> Result<Value, Error> {
+        let mut line = String::new();
+        stdout
+            .read_line(&mut line)
+            .map_err(|e| credential_error(name, e))?;
+        serde_json::from_str(&line).map_err(|e| credential_error(name, e))
+    };
+
+    let hello = read()?;
+    let supported = hello["v"]
+        .as_array()
+        .is_some_and(|versions| versions.contains(&json!(1)));
+    if !supported {
+        return Err(credential_error(
+            name,
+            "unsupported provider protocol version",
+        ));
+    }
+
+    let request = json!({
+        "v": 1,
+        "registry": { "index-url": index_url, "name": name },
+        "kind": "get",
+        "operation": "read",
+        "args": &command[1..],
+    });
+    writeln!(stdin, "{}", request).map_err(|e| credential_error(name, e))?;
+    let response = read()?;
+    // Closing stdin tells the provider to exit.
+    drop(stdin);
+    let _ = child.wait();
+
+    if let Some(token) = response.pointer("/Ok/token").and_then(Value::as_str) {
+        return Ok(Some(token.to_string()));
+    }
+    match response.pointer("/Err/kind").and_then(Value::as_str) {
+        Some("not-found") | Some("url-not-supported") => Ok(None),
+        _ => {
+            let message = response
+                .pointer("/Err/message")
+                .and_then(Value::as_str)
+                .unwrap_or("invalid response");
+            Err(credential_error(name, message))
+        }
+    }
+}
+
+fn credential_error(name: &str, e: impl std::fmt::Display) -> Error {
+    Error::Credential(format!("registry `{}`: {}", name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    use std::{env, fs, path::Path};
+
+    const INDEX_URL: &str = "sparse+https://example.com/index/";
+
+    /// Loads a configuration from the given `config.toml` and
+    /// `credentials.toml`, both stored in the Cargo home directory `dir`.
+    fn config(dir: &Path, config: &str, credentials: Option<&str>) -> CargoConfig {
+        fs::write(dir.join("config.toml"), config).unwrap();
+        if let Some(credentials) = credentials {
+            fs::write(dir.join("credentials.toml"), credentials).unwrap();
+        }
+        CargoConfig::load_from(dir, Some(dir))
+    }
+
+    /// Writes a credential provider answering a `get` request with
+    /// `response`, and logging the request it received to `request.json`.
+    fn write_provider(dir: &Path, response: &str) -> String {
+        let path = dir.join("provider.sh");
+        let script = format!(
+            "echo '{{\"v\":[1]}}'\nread request\necho \"$request\" > {}\necho '{}'\n",
+            dir.join("request.json").display(),
+            response
+        );
+        fs::write(&path, script).unwrap();
+        format!(r#"["sh", "{}", "--extra"]"#, path.display())
+    }
+
+    #[test]
+    fn credentials_file_overrides_config() {
+        let dir = temp_dir("credentials-file");
+        let config = config(
+            &dir,
+            "[registries.cargo-free-test-file]\ntoken = \"from-config\"",
+            Some("[registries.cargo-free-test-file]\ntoken = \"from-credentials\""),
+        );
+        assert_eq!(
+            registry_token(&config, "cargo-free-test-file", INDEX_URL),
+            Ok(Some("from-credentials".to_string()))
+        );
+        assert_eq!(
+            registry_token(&config, "cargo-free-test-other", INDEX_URL),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn environment_overrides_credentials_file() {
+        env::set_var("CARGO_REGISTRIES_CARGO_FREE_TEST_ENV_TOKEN", "from-env");
+        let dir = temp_dir("credentials-env");
+        let config = config(
+            &dir,
+            "",
+            Some("[registries.cargo-free-test-env]\ntoken = \"from-credentials\""),
+        );
+        assert_eq!(
+            registry_token(&config, "cargo-free-test-env", INDEX_URL),
+            Ok(Some("from-env".to_string()))
+        );
+    }
+
+    #[test]
+    fn token_from_stdout() {
+        let dir = temp_dir("credentials-stdout");
+        let config = config(
+            &dir,
+            r#"[registries.cargo-free-test-stdout]
+credential-provider = ["cargo:token-from-stdout", "sh", "-c", "echo $CARGO_REGISTRY_NAME_OPT@$CARGO_REGISTRY_INDEX_URL"]"#,
+            None,
+        );
+        assert_eq!(
+            registry_token(&config, "cargo-free-test-stdout", INDEX_URL),
+            Ok(Some(format!("cargo-free-test-stdout@{}", INDEX_URL)))
+        );
+    }
+
+    #[test]
+    fn failing_token_from_stdout() {
+        let dir = temp_dir("credentials-stdout-failing");
+        let config = config(
+            &dir,
+            "[registries.cargo-free-test-failing]\ncredential-provider = \"cargo:token-from-stdout false\"",
+            None,
+        );
+        assert!(matches!(
+            registry_token(&config, "cargo-free-test-failing", INDEX_URL),
+            Err(Error::Credential(_))
+        ));
+    }
+
+    #[test]
+    fn provider_protocol() {
+        let dir = temp_dir("credentials-provider");
+        let provider = write_provider(
+            &dir,
+            r#"{"Ok":{"kind":"get","token":"from-provider","cache":"session","operation_independent":true}}"#,
+        );
+        let config = config(
+            &dir,
+            &format!(
+                "[registries.cargo-free-test-provider]\ncredential-provider = {}",
+                provider
+            ),
+            None,
+        );
+        assert_eq!(
+            registry_token(&config, "cargo-free-test-provider", INDEX_URL),
+            Ok(Some("from-provider".to_string()))
+        );
+
+        let request = fs::read_to_string(dir.join("request.json")).unwrap();
+        let request: Value = serde_json::from_str(&request).unwrap();
+        assert_eq!(
+            request,
+            json!({
+                "v": 1,
+                "registry": { "index-url": INDEX_URL, "name": "cargo-free-test-provider" },
+                "kind": "get",
+                "operation": "read",
+                "args": [dir.join("provider.sh").display().to_string(), "--extra"],
+            })
+        );
+    }
+
+    #[test]
+    fn provider_errors() {
+        let dir = temp_dir("credentials-provider-error");
+        let provider = write_provider(&dir, r#"{"Err":{"kind":"other","message":"locked"}}"#);
+        let config = config(
+            &dir,
+            &format!(
+                "[registries.cargo-free-test-error]\ncredential-provider = {}",
+                provider
+            ),
+            None,
+        );
+        assert_eq!(
+            registry_token(&config, "cargo-free-test-error", INDEX_URL),
+            Err(Error::Credential(
+                "registry `cargo-free-test-error`: locked".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn unsupported_protocol_version() {
+        let dir = temp_dir("credentials-provider-version");
+        let path = dir.join("provider.sh");
+        fs::write(&path, "echo '{\"v\":[2]}'\n").unwrap();
+        let config = config(
+            &dir,
+            &format!(
+                "[registries.cargo-free-test-version]\ncredential-provider = [\"sh\", \"{}\"]",
+                path.display()
+            ),
+            None,
+        );
+        assert!(matches!(
+            registry_token(&config, "cargo-free-test-version", INDEX_URL),
+            Err(Error::Credential(_))
+        ));
+    }
+
+    #[test]
+    fn global_providers_fall_through() {
+        let dir = temp_dir("credentials-global");
+        let provider = write_provider(&dir, r#"{"Err":{"kind":"not-found"}}"#);
+        let config = config(
+            &dir,
+            &format!(
+                r#"[registry]
+global-credential-providers = ["cargo:token", "my-provider"]
+
+[credential-alias]
+my-provider = {}
+
+[registries.cargo-free-test-global]
+token = "from-config""#,
+                provider
+            ),
+            None,
+        );
+        // The provider listed last is asked first.
+        assert_eq!(
+            registry_token(&config, "cargo-free-test-global", INDEX_URL),
+            Ok(Some("from-config".to_string()))
+        );
+        assert!(dir.join("request.json").is_file());
+    }
+}
@@ -0,0 +1,220 @@
+//! Heuristics telling abandoned crates apart from maintained ones.
+//!
+//! The owners of a crate that has not seen a release in years may be willing
+//! to transfer its name, see `cargo free adopt`.
+
+use crate::CrateInfo;
+use std::time::{Duration, SystemTime};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Crates without a release for longer are considered dormant.
+const DORMANT_AFTER: Duration = Duration::from_secs(2 * 365 * 24 * 60 * 60);
+
+/// The period `recent_downloads` covers, in days.
+const RECENT_DAYS: f64 = 90.0;
+
+/// Maintenance badges declaring that a crate is no longer maintained.
+const UNMAINTAINED_STATUSES: &[&str] = &["deprecated", "as-is", "looking-for-maintainer"];
+
+/// Words in descriptions declaring that a crate is no longer maintained.
+const UNMAINTAINED_WORDS: &[&str] = &[
+    "deprecated",
+    "unmaintained",
+    "archived",
+    "abandoned",
+    "obsolete",
+];
+
+/// How the downloads of a crate developed recently.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DownloadTrend {
+    /// The crate is downloaded more often than it used to be.
+    Rising,
+
+    /// The crate is downloaded about as often as it used to be.
+    Steady,
+
+    /// The crate is downloaded less often than it used to be.
+    Falling,
+}
+
+/// Whether a crate still seems to be in use and maintained, see
+/// [`CrateInfo::dormancy`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Dormancy {
+    /// The time the latest version was published, or the crate was last
+    /// updated if the publication times are unknown.
+    pub last_published: Option<SystemTime>,
+
+    /// Set if the crate is declared unmaintained by its maintenance badge or
+    /// description, e.g. as deprecated or archived.
+    pub unmaintained: bool,
+
+    /// How the downloads developed in the last 90 days, compared to the
+    /// average since the crate was created.
+    pub download_trend: Option<DownloadTrend>,
+}
+
+impl Dormancy {
+    /// Returns the time passed since the latest version was published.
+    pub fn idle_for(&self) -> Option<Duration> {
+        SystemTime::now().duration_since(self.last_published?).ok()
+    }
+
+    /// Returns `true` if the crate is declared unmaintained, or has not been
+    /// published for two years and is not gaining users.
+    pub fn is_dormant(&self) -> bool {
+        let idle = self.idle_for().is_some_and(|idle| idle > DORMANT_AFTER);
+        self.unmaintained || (idle && self.download_trend != Some(DownloadTrend::Rising))
+    }
+}
+
+impl CrateInfo {
+    /// Analyzes whether the crate is still maintained, based on its latest
+    /// release, maintenance status and download trend. The analysis is most
+    /// meaningful with the metadata of the crates.io API.
+    pub fn dormancy(&self) -> Dormancy {
+        let unmaintained = self
+            .maintenance
+            .as_deref()
+            .is_some_and(|status| UNMAINTAINED_STATUSES.contains(&status))
+            || self.description.as_deref().is_some_and(|description| {
+                description
+                    .to_lowercase()
+                    .split(|c: char| !c.is_alphanumeric())
+                    .any(|word| UNMAINTAINED_WORDS.contains(&word))
+            });
+
+        Dormancy {
+            last_published: self.published_at.or(self.updated_at),
+            unmaintained,
+            download_trend: self.download_trend(),
+        }
+    }
+
+    /// Compares the recent downloads to the number expected if the crate
+    /// had been downloaded at a constant rate since its creation.
+    fn download_trend(&self) -> Option<DownloadTrend> {
+        let age = SystemTime::now().duration_since(self.created_at?).ok()?;
+        let days = age.as_secs_f64() / DAY.as_secs_f64();
+        // Young crates have no history to compare against.
+        if days < 2.0 * RECENT_DAYS {
+            return None;
+        }
+
+        let expected = self.downloads? as f64 * RECENT_DAYS / days;
+        let ratio = self.recent_downloads? as f64 / expected.max(1.0);
+        Some(if ratio >= 1.5 {
+            DownloadTrend::Rising
+        } else if ratio >= 0.5 {
+            DownloadTrend::Steady
+        } else {
+            DownloadTrend::Falling
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(age_days: u64, downloads: u64, recent_downloads: u64) -> CrateInfo {
+        let mut info = CrateInfo::new("foo");
+        info.created_at = Some(SystemTime::now() - age_days as u32 * DAY);
+        info.downloads = Some(downloads);
+        info.recent_downloads = Some(recent_downloads);
+        info
+    }
+
+    #[test]
+    fn download_trend_thresholds() {
+        // 3600 downloads in 360 days are 900 per 90 days.
+        assert_eq!(
+            info(360, 3600, 1350).download_trend(),
+            Some(DownloadTrend::Rising)
+        );
+        assert_eq!(
+            info(360, 3600, 1349).download_trend(),
+            Some(DownloadTrend::Steady)
+        );
+        assert_eq!(
+            info(360, 3600, 450).download_trend(),
+            Some(DownloadTrend::Steady)
+        );
+        assert_eq!(
+            info(360, 3600, 449).download_trend(),
+            Some(DownloadTrend::Falling)
+        );
+    }
+
+    #[test]
+    fn download_trend_needs_history() {
+        assert_eq!(info(179, 3600, 0).download_trend(), None);
+        assert_eq!(CrateInfo::new("foo").download_trend(), None);
+        // Crates that are hardly downloaded are compared to one download.
+        assert_eq!(
+            info(360, 0, 0).download_trend(),
+            Some(DownloadTrend::Falling)
+        );
+        assert_eq!(
+            info(360, 0, 1).download_trend(),
+            Some(DownloadTrend::Steady)
+        );
+    }
+
+    #[test]
+    fn idle_crates_are_dormant_unless_rising() {
+        let mut info = info(4 * 365, 3600, 10);
+        info.published_at = Some(SystemTime::now() - 3 * 365 * DAY);
+        assert!(info.dormancy().is_dormant());
+
+        info.recent_downloads = Some(10_000);
+        assert_eq!(info.dormancy().download_trend, Some(DownloadTrend::Rising));
+        assert!(!info.dormancy().is_dormant());
+    }
+
+    #[test]
+    fn recently_published_crates_are_not_dormant() {
+        let mut info = info(4 * 365, 3600, 10);
+        info.published_at = Some(SystemTime::now() - 365 * DAY);
+        // Without publication times, the last update is used.
+        info.updated_at = Some(SystemTime::now() - 3 * 365 * DAY);
+        let dormancy = info.dormancy();
+        assert_eq!(dormancy.last_published, info.published_at);
+        assert!(!dormancy.is_dormant());
+
+        info.published_at = None;
+        assert!(info.dormancy().is_dormant());
+    }
+
+    #[test]
+    fn unmaintained_crates_are_dormant() {
+        let mut info = info(30, 100, 100);
+        info.published_at = Some(SystemTime::now());
+        assert!(!info.dormancy().is_dormant());
+
+        info.maintenance = Some("looking-for-maintainer".to_string());
+        assert!(info.dormancy().unmaintained);
+        assert!(info.dormancy().is_dormant());
+
+        info.maintenance = Some("actively-developed".to_string());
+        assert!(!info.dormancy().unmaintained);
+    }
+
+    #[test]
+    fn unmaintained_words() {
+        let unmaintained = |description: &str| {
+            let mut info = CrateInfo::new("foo");
+            info.description = Some(description.to_string());
+            info.dormancy().unmaintained
+        };
+        assert!(unmaintained("DEPRECATED: use bar instead"));
+        assert!(unmaintained("An (unmaintained) parser."));
+        assert!(unmaintained("Archived, see bar"));
+        assert!(!unmaintained("Finds deprecations in your code"));
+        assert!(!unmaintained("Handles archives"));
+        assert!(!unmaintained("An obsolescence tracker"));
+    }
+}
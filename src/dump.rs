@@ -0,0 +1,377 @@
+//! Support for the crates.io database dump, published daily at
+//! `https://static.crates.io/db-dump.tar.gz`.
+//!
+//! Importing a dump extracts the crate names and a few facts about each
+//! crate into a compact local store, which
+//! [`BackendKind::DbDump`](crate::BackendKind::DbDump) then queries.
+
+use crate::{name::canonical_name, Error};
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The first line of every store file, followed by the dump's timestamp.
+const STORE_HEADER: &str = "# cargo-free crates.io db dump";
+
+/// A crate as recorded in the database dump.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct DumpCrate {
+    pub(crate) name: String,
+    pub(crate) created_at: Option<SystemTime>,
+    pub(crate) downloads: Option<u64>,
+}
+
+/// The crates of an imported database dump, keyed by canonical name.
+#[derive(Debug)]
+pub(crate) struct DumpStore {
+    pub(crate) as_of: SystemTime,
+    pub(crate) crates: HashMap<String, DumpCrate>,
+}
+
+impl DumpStore {
+    /// Loads a store previously written by [`import_db_dump`].
+    pub(crate) fn load(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        let mut lines = BufReader::new(file).lines();
+
+        let header = lines
+            .next()
+            .transpose()
+            .map_err(|e| Error::io(path, e))?
+            .unwrap_or_default();
+        let as_of = header
+            .strip_prefix(STORE_HEADER)
+            .and_then(|timestamp| timestamp.trim().parse().ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+            .ok_or_else(|| Error::Io(format!("{}: not a db dump store", path.display())))?;
+
+        let mut crates = HashMap::new();
+        for line in lines {
+            let line = line.map_err(|e| Error::io(path, e))?;
+            let mut fields = line.split('\t');
+            let name = match fields.next() {
+                Some(name) if !name.is_empty() => name.to_string(),
+                _ => continue,
+            };
+            let created_at = fields
+                .next()
+                .and_then(|secs| secs.parse().ok())
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+            let downloads = fields.next().and_then(|downloads| downloads.parse().ok());
+
+            crates.insert(
+                canonical_name(&name),
+                DumpCrate {
+                    name,
+                    created_at,
+                    downloads,
+                },
+            );
+        }
+
+        Ok(Self { as_of, crates })
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+
+        // Write to a temporary file first, so an interrupted import does not
+        // destroy an existing store.
+        let tmp = path.with_extension("tmp");
+        let file = File::create(&tmp).map_err(|e| Error::io(&tmp, e))?;
+        let mut writer = BufWriter::new(file);
+        let mut crates = self.crates.values().collect::<Vec<_>>();
+        crates.sort_by(|a, b| a.name.cmp(&b.name));
+
+        writeln!(writer, "{} {}", STORE_HEADER, unix_secs(self.as_of))
+            .map_err(|e| Error::io(&tmp, e))?;
+        for krate in crates {
+            writeln!(
+                writer,
+                "{}\t{}\t{}",
+                krate.name,
+                krate
+                    .created_at
+                    .map(|time| unix_secs(time).to_string())
+                    .unwrap_or_default(),
+                krate.downloads.map(|d| d.to_string()).unwrap_or_default(),
+            )
+            .map_err(|e| Error::io(&tmp, e))?;
+        }
+        writer.flush().map_err(|e| Error::io(&tmp, e))?;
+        drop(writer);
+
+        fs::rename(&tmp, path).map_err(|e| Error::io(path, e))
+    }
+}
+
+/// Returns the default location of the db dump store, inside the user's data
+/// directory (e.g. `~/.local/share/cargo-free/db-dump.tsv` on Linux).
+pub fn default_db_dump_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("cargo-free").join("db-dump.tsv"))
+}
+
+/// Imports the crates of a crates.io database dump (`db-dump.tar.gz`) into the
+/// store at `store`, replacing its previous content.
+///
+/// # Returns
+///
+/// The number of imported crates. Returns `Err(Error::Io)` if the archive
+/// can't be read or does not contain a `crates.csv` file.
+pub fn import_db_dump(archive: impl AsRef<Path>, store: impl AsRef<Path>) -> Result<usize, Error> {
+    let archive = archive.as_ref();
+    let file = File::open(archive).map_err(|e| Error::io(archive, e))?;
+    let mut tar = tar::Archive::new(GzDecoder::new(file));
+
+    let mut as_of = None;
+    let mut crates = None;
+    let mut downloads = HashMap::new();
+    for entry in tar.entries().map_err(|e| Error::io(archive, e))? {
+        let entry = entry.map_err(|e| Error::io(archive, e))?;
+        let path = entry.path().map_err(|e| Error::io(archive, e))?;
+        if path.ends_with("metadata.json") {
+            as_of = read_timestamp(entry);
+        } else if path.ends_with("data/crates.csv") {
+            crates = Some(read_crates(entry).map_err(|e| dump_error(archive, e))?);
+        } else if path.ends_with("data/crate_downloads.csv") {
+            // Newer dumps keep the download counts in a separate table.
+            downloads = read_downloads(entry).map_err(|e| dump_error(archive, e))?;
+        }
+    }
+
+    let crates =
+        crates.ok_or_else(|| Error::Io(format!("{}: no crates.csv found", archive.display())))?;
+    let crates = crates
+        .into_iter()
+        .map(|(id, mut krate)| {
+            if let Some(&count) = downloads.get(&id) {
+                krate.downloads = Some(count);
+            }
+            (canonical_name(&krate.name), krate)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let store_data = DumpStore {
+        as_of: as_of.unwrap_or_else(SystemTime::now),
+        crates,
+    };
+    store_data.save(store.as_ref())?;
+    Ok(store_data.crates.len())
+}
+
+fn read_timestamp(reader: impl Read) -> Option<SystemTime> {
+    let metadata: Value = serde_json::from_reader(reader).ok()?;
+    parse_timestamp(metadata.get("timestamp")?.as_str()?)
+}
+
+/// Reads `crates.csv`, returning the crates keyed by their database id.
+fn read_crates(reader: impl Read) -> Result<Vec<(String, DumpCrate)>, csv::Error> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let (id, name, created_at, downloads) = (
+        column("id"),
+        column("name"),
+        column("created_at"),
+        column("downloads"),
+    );
+
+    let mut crates = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |column: Option<usize>| column.and_then(|column| record.get(column));
+        let name = match field(name) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        crates.push((
+            field(id).unwrap_or_default().to_string(),
+            DumpCrate {
+                name,
+                created_at: field(created_at).and_then(parse_timestamp),
+                downloads: field(downloads).and_then(|downloads| downloads.parse().ok()),
+            },
+        ));
+    }
+    Ok(crates)
+}
+
+/// Reads `crate_downloads.csv`, returning the download counts keyed by crate
+/// id.
+fn read_downloads(reader: impl Read) -> Result<HashMap<String, u64>, csv::Error> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let (id, downloads) = match (column("crate_id"), column("downloads")) {
+        (Some(id), Some(downloads)) => (id, downloads),
+        _ => return Ok(HashMap::new()),
+    };
+
+    let mut counts = HashMap::new();
+    for record in reader.records() {
+        let record = record?;
+        if let (Some(id), Some(Ok(count))) = (
+            record.get(id),
+            record.get(downloads).map(|count| count.parse()),
+        ) {
+            counts.insert(id.to_string(), count);
+        }
+    }
+    Ok(counts)
+}
+
+/// Parses the timestamps used by the dump, e.g. `2015-02-24 08:45:44.567564`
+/// or `2024-05-01T02:00:12Z`.
+pub(crate) fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+    let timestamp = timestamp.trim();
+    let timestamp = timestamp
+        .strip_suffix("+00:00")
+        .or_else(|| timestamp.strip_suffix("+00"))
+        .unwrap_or(timestamp);
+    humantime::parse_rfc3339_weak(timestamp).ok()
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+fn dump_error(archive: &Path, e: csv::Error) -> Error {
+    Error::Io(format!("{}: malformed db dump: {}", archive.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    /// Writes a `db-dump.tar.gz` containing the given files.
+    fn write_archive(path: &Path, files: &[(&str, &str)]) {
+        let file = File::create(path).unwrap();
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            file,
+            flate2::Compression::fast(),
+        ));
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn load_saved_store() {
+        let path = temp_dir("dump-store").join("db-dump.tsv");
+        let mut crates = HashMap::new();
+        for krate in [
+            DumpCrate {
+                name: "Foo-Bar".to_string(),
+                created_at: Some(at(1_500_000_000)),
+                downloads: Some(42),
+            },
+            DumpCrate {
+                name: "baz".to_string(),
+                created_at: None,
+                downloads: None,
+            },
+        ] {
+            crates.insert(canonical_name(&krate.name), krate);
+        }
+        let store = DumpStore {
+            as_of: at(1_600_000_000),
+            crates,
+        };
+        store.save(&path).unwrap();
+
+        let loaded = DumpStore::load(&path).unwrap();
+        assert_eq!(loaded.as_of, store.as_of);
+        assert_eq!(loaded.crates, store.crates);
+    }
+
+    #[test]
+    fn load_rejects_other_files() {
+        let path = temp_dir("dump-invalid").join("db-dump.tsv");
+        fs::write(&path, "serde\t\t\n").unwrap();
+        assert!(matches!(DumpStore::load(&path), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(
+            parse_timestamp("2017-07-14 02:40:00.123"),
+            Some(at(1_500_000_000) + Duration::from_millis(123))
+        );
+        assert_eq!(
+            parse_timestamp("2017-07-14T02:40:00Z"),
+            Some(at(1_500_000_000))
+        );
+        assert_eq!(
+            parse_timestamp("2017-07-14 02:40:00+00"),
+            Some(at(1_500_000_000))
+        );
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn import() {
+        let dir = temp_dir("dump-import");
+        let archive = dir.join("db-dump.tar.gz");
+        write_archive(
+            &archive,
+            &[
+                (
+                    "2017-07-14-020000/metadata.json",
+                    r#"{"timestamp":"2017-07-14T02:40:00Z"}"#,
+                ),
+                (
+                    "2017-07-14-020000/data/crates.csv",
+                    "id,name,created_at,downloads\n\
+                     1,Foo-Bar,2015-02-24 08:45:44.567564,10\n\
+                     2,baz,,\n",
+                ),
+                (
+                    "2017-07-14-020000/data/crate_downloads.csv",
+                    "crate_id,downloads\n1,42\n",
+                ),
+            ],
+        );
+
+        let path = dir.join("db-dump.tsv");
+        assert_eq!(import_db_dump(&archive, &path).unwrap(), 2);
+        let store = DumpStore::load(&path).unwrap();
+        assert_eq!(store.as_of, at(1_500_000_000));
+        let krate = &store.crates["foo_bar"];
+        assert_eq!(krate.name, "Foo-Bar");
+        assert_eq!(krate.created_at, Some(at(1_424_767_544)));
+        assert_eq!(krate.downloads, Some(42));
+        assert_eq!(store.crates["baz"].created_at, None);
+    }
+
+    #[test]
+    fn import_requires_crates() {
+        let dir = temp_dir("dump-import-empty");
+        let archive = dir.join("db-dump.tar.gz");
+        write_archive(&archive, &[("metadata.json", "{}")]);
+        assert!(matches!(
+            import_db_dump(&archive, dir.join("db-dump.tsv")),
+            Err(Error::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,724 @@
+use crate::{config::CargoConfig, Error};
+use std::{
+    convert::TryFrom,
+    env,
+    fs::File,
+    io::{BufReader, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// The number of retries Cargo performs if `net.retry` is not configured.
+const CARGO_DEFAULT_RETRIES: u32 = 3;
+
+/// Controls how often and how long failed requests are retried.
+///
+/// Only transient failures are retried, see [`Error::is_transient`]. The
+/// delay doubles after every attempt, up to `max_delay`. If the registry
+/// asks to wait longer using `Retry-After`, its delay is used instead.
+///
+/// ```
+/// use cargo_free::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::new(5, Duration::from_millis(500))
+///     .max_delay(Duration::from_secs(10))
+///     .deadline(Duration::from_secs(30));
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of retries after the initial attempt.
+    pub max_retries: u32,
+
+    /// The time to wait before the first retry.
+    pub delay: Duration,
+
+    /// The maximum time to wait between two attempts, unless the registry
+    /// asks for a longer delay.
+    pub max_delay: Duration,
+
+    /// Whether to randomize delays, so concurrent checks don't retry in
+    /// lockstep. Randomized delays lie between half and all of the regular
+    /// delay.
+    pub jitter: bool,
+
+    /// The maximum time spent on a request including all retries. No retry
+    /// is attempted if its delay would exceed the deadline.
+    pub deadline: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self::new(0, Duration::from_secs(0))
+    }
+
+    /// A policy that retries up to `max_retries` times with exponential
+    /// backoff, waiting `delay` before the first retry. Delays are capped at
+    /// 30 seconds and randomized.
+    pub fn new(max_retries: u32, delay: Duration) -> Self {
+        Self {
+            max_retries,
+            delay,
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            deadline: None,
+        }
+    }
+
+    /// A policy mirroring Cargo's, using the number of retries configured via
+    /// `net.retry` or `CARGO_NET_RETRY` (three by default).
+    pub fn from_cargo_config() -> Self {
+        let retries = CargoConfig::load()
+            .integer("net.retry")
+            .and_then(|retries| u32::try_from(retries).ok())
+            .unwrap_or(CARGO_DEFAULT_RETRIES);
+        Self::new(retries, Duration::from_secs(1))
+    }
+
+    /// Sets the maximum time to wait between two attempts.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Enables or disables randomized delays.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the maximum time spent on a request including all retries.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the delay before the retry following `retries` previous ones.
+    fn backoff(&self, retries: u32) -> Duration {
+        let delay = self
+            .delay
+            .checked_mul(2u32.saturating_pow(retries))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        if self.jitter {
+            delay / 2 + delay.mul_f64(fastrand::f64()) / 2
+        } else {
+            delay
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Limits the number of requests sent to the registry.
+///
+/// Requests are admitted using a token bucket holding up to `burst` tokens,
+/// which refills with `requests` tokens per `per`. All checks of a checker
+/// share the same bucket.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RateLimit {
+    /// The number of requests allowed per `per`.
+    pub requests: u32,
+
+    /// The period the number of requests refers to.
+    pub per: Duration,
+
+    /// The number of requests that may be sent at once after a period of
+    /// inactivity.
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// No limit at all.
+    pub fn none() -> Self {
+        Self::new(u32::MAX, Duration::from_secs(1))
+    }
+
+    /// Allows `requests` requests per `per`, without bursts.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        Self {
+            requests,
+            per,
+            burst: 1,
+        }
+    }
+
+    /// At most one request per second, as required by the crates.io data
+    /// access policy for the API.
+    pub fn crates_io() -> Self {
+        Self::new(1, Duration::from_secs(1))
+    }
+
+    /// Sets the number of requests that may be sent at once.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.requests == u32::MAX
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// The state of a [`RateLimit`]'s token bucket.
+#[derive(Debug)]
+struct TokenBucket {
+    limit: RateLimit,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: Mutex::new((f64::from(limit.burst.max(1)), Instant::now())),
+        }
+    }
+
+    /// Waits until a request may be sent.
+    fn acquire(&self) {
+        if self.limit.is_unlimited() || self.limit.requests == 0 {
+            return;
+        }
+
+        let rate = f64::from(self.limit.requests) / self.limit.per.as_secs_f64();
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            let (tokens, last) = &mut *state;
+            let now = Instant::now();
+            let capacity = f64::from(self.limit.burst.max(1));
+            *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * rate).min(capacity);
+            *last = now;
+            // Take the token right away, even if it is yet to be refilled.
+            // Waiting callers thus queue up in order.
+            *tokens -= 1.0;
+            if *tokens < 0.0 {
+                Duration::from_secs_f64(-*tokens / rate)
+            } else {
+                Duration::from_secs(0)
+            }
+        };
+        thread::sleep(wait);
+    }
+}
+
+/// Returns the `User-Agent` sent by default, e.g.
+/// `cargo-free/0.6.0 (+https://github.com/SirWindfield/cargo-free)`.
+///
+/// Contact information, as recommended by the crates.io data access policy,
+/// is read from the `free.contact` key of Cargo's configuration or the
+/// `CARGO_FREE_CONTACT` environment variable.
+pub fn default_user_agent() -> String {
+    user_agent(CargoConfig::load().string("free.contact").as_deref())
+}
+
+/// Returns the default `User-Agent` including the given contact information.
+pub(crate) fn user_agent(contact: Option<&str>) -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    match contact {
+        Some(contact) => format!(
+            "cargo-free/{} (+https://github.com/SirWindfield/cargo-free; {})",
+            version, contact
+        ),
+        None => format!(
+            "cargo-free/{} (+https://github.com/SirWindfield/cargo-free)",
+            version
+        ),
+    }
+}
+
+/// The settings of an [`Http`] client.
+#[derive(Clone, Debug)]
+pub(crate) struct HttpSettings {
+    pub(crate) timeout: Duration,
+    pub(crate) user_agent: String,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) rate_limit: RateLimit,
+
+    /// The proxy to use for all requests. If unset, the proxy is taken from
+    /// the `HTTPS_PROXY`, `https_proxy` and `http_proxy` environment
+    /// variables, honoring `NO_PROXY`.
+    pub(crate) proxy: Option<String>,
+
+    /// A PEM file with the certificates to trust instead of the bundled
+    /// ones.
+    pub(crate) ca_bundle: Option<PathBuf>,
+}
+
+/// A configured HTTP client shared by all network backends of a checker.
+#[derive(Clone)]
+pub(crate) struct Http {
+    agent: ureq::Agent,
+    timeout: Duration,
+    user_agent: String,
+    retry: RetryPolicy,
+    limiter: Arc<TokenBucket>,
+    proxies: Proxies,
+    tls_config: Option<Arc<rustls::ClientConfig>>,
+
+    /// An invalid proxy or CA bundle, reported by every request instead of
+    /// silently bypassing it.
+    setup_error: Option<Error>,
+}
+
+impl Http {
+    pub(crate) fn new(settings: HttpSettings) -> Self {
+        let mut setup_error = None;
+        let proxies = Proxies::new(settings.proxy.as_deref()).unwrap_or_else(|e| {
+            setup_error = Some(e);
+            Proxies::default()
+        });
+        let tls_config = match settings.ca_bundle.as_deref().map(tls_config) {
+            Some(Ok(config)) => Some(Arc::new(config)),
+            Some(Err(e)) => {
+                setup_error = Some(e);
+                None
+            }
+            None => None,
+        };
+
+        Self {
+            agent: ureq::Agent::new(),
+            timeout: settings.timeout,
+            user_agent: settings.user_agent,
+            retry: settings.retry,
+            limiter: Arc::new(TokenBucket::new(settings.rate_limit)),
+            proxies,
+            tls_config,
+            setup_error,
+        }
+    }
+
+    /// Sends a `GET` request to `url`, retrying transient failures according
+    /// to the retry policy. Retries are logged as warnings. Every attempt
+    /// waits for the rate limit.
+    ///
+    /// Transport failures, rate limits and server errors are turned into
+    /// their respective `Error` variants. All other responses are returned
+    /// as-is.
+    pub(crate) fn get(&self, url: &str) -> Result<ureq::Response, Error> {
+        self.get_authorized(url, None)
+    }
+
+    /// Sends a `GET` request like [`Http::get`], passing `token` in the
+    /// `Authorization` header.
+    pub(crate) fn get_authorized(
+        &self,
+        url: &str,
+        token: Option<&str>,
+    ) -> Result<ureq::Response, Error> {
+        if let Some(e) = &self.setup_error {
+            return Err(e.clone());
+        }
+
+        let start = Instant::now();
+        let mut retries = 0;
+        loop {
+            self.limiter.acquire();
+            let mut request = self.agent.get(url);
+            request.timeout(self.timeout);
+            request.set("User-Agent", &self.user_agent);
+            if let Some(proxy) = self.proxies.for_url(url) {
+                request.set_proxy(proxy.clone());
+            }
+            if let Some(token) = token {
+                request.set("Authorization", token);
+            }
+            if let Some(tls_config) = &self.tls_config {
+                request.set_tls_config(Arc::clone(tls_config));
+            }
+
+            let e = match self.classify(request.call()) {
+                Err(e) if e.is_transient() => e,
+                result => return result,
+            };
+            if retries >= self.retry.max_retries {
+                return Err(e);
+            }
+
+            let mut delay = self.retry.backoff(retries);
+            if let Error::RateLimited {
+                retry_after: Some(retry_after),
+            } = e
+            {
+                delay = delay.max(retry_after);
+            }
+            if let Some(deadline) = self.retry.deadline {
+                if start.elapsed() + delay > deadline {
+                    return Err(e);
+                }
+            }
+
+            retries += 1;
+            log::warn!(
+                "{}: {}, retrying in {} ({} of {})",
+                url,
+                e,
+                humantime::format_duration(Duration::from_millis(delay.as_millis() as u64)),
+                retries,
+                self.retry.max_retries
+            );
+            thread::sleep(delay);
+        }
+    }
+
+    fn classify(&self, response: ureq::Response) -> Result<ureq::Response, Error> {
+        if let Some(e) = response.synthetic_error() {
+            return Err(match e {
+                ureq::Error::Io(e)
+                    if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock =>
+                {
+                    Error::NetworkTimeout(self.timeout)
+                }
+                e => Error::Transport(e.to_string()),
+            });
+        }
+
+        match response.status() {
+            status @ (401 | 403) => Err(Error::Unauthorized(status)),
+            408 => Err(Error::NetworkTimeout(self.timeout)),
+            429 => Err(Error::RateLimited {
+                retry_after: response.header("Retry-After").and_then(parse_retry_after),
+            }),
+            status @ 500..=599 => Err(Error::ServerError(status)),
+            _ => Ok(response),
+        }
+    }
+}
+
+/// Parses the value of a `Retry-After` header, which is either a number of
+/// seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or_else(|_| Duration::from_secs(0)),
+    )
+}
+
+/// The proxies to use, by URL scheme.
+#[derive(Clone, Debug, Default)]
+struct Proxies {
+    all: Option<ureq::Proxy>,
+    https: Option<ureq::Proxy>,
+    http: Option<ureq::Proxy>,
+    no_proxy: Vec<String>,
+}
+
+impl Proxies {
+    /// Uses `proxy` for all requests if set, otherwise the proxies configured
+    /// in the environment, like curl (and thus Cargo) does.
+    fn new(proxy: Option<&str>) -> Result<Self, Error> {
+        if let Some(proxy) = proxy {
+            return Ok(Self {
+                all: Some(parse_proxy(proxy)?),
+                ..Self::default()
+            });
+        }
+
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| env::var(name).ok())
+                .filter(|value| !value.is_empty())
+        };
+        Ok(Self {
+            all: None,
+            https: var(&["HTTPS_PROXY", "https_proxy"])
+                .map(|proxy| parse_proxy(&proxy))
+                .transpose()?,
+            http: var(&["http_proxy"])
+                .map(|proxy| parse_proxy(&proxy))
+                .transpose()?,
+            no_proxy: var(&["NO_PROXY", "no_proxy"])
+                .unwrap_or_default()
+                .split(',')
+                .map(|host| host.trim().trim_start_matches('.').to_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect(),
+        })
+    }
+
+    fn for_url(&self, url: &str) -> Option<&ureq::Proxy> {
+        if self.all.is_some() {
+            return self.all.as_ref();
+        }
+
+        let (scheme, rest) = url.split_once("://")?;
+        let host = rest
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        let excluded = self.no_proxy.iter().any(|pattern| {
+            pattern == "*" || host == *pattern || host.ends_with(&format!(".{}", pattern))
+        });
+        match scheme {
+            _ if excluded => None,
+            "https" => self.https.as_ref(),
+            "http" => self.http.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+fn parse_proxy(proxy: &str) -> Result<ureq::Proxy, Error> {
+    // ureq does not accept a trailing slash after the port.
+    ureq::Proxy::new(proxy.trim_end_matches('/'))
+        .map_err(|e| Error::Transport(format!("invalid proxy `{}`: {}", proxy, e)))
+}
+
+/// Creates a TLS configuration trusting the certificates in the PEM file at
+/// `path`.
+fn tls_config(path: &Path) -> Result<rustls::ClientConfig, Error> {
+    let file = File::open(path).map_err(|e| Error::io(path, e))?;
+    let mut config = rustls::ClientConfig::new();
+    match config.root_store.add_pem_file(&mut BufReader::new(file)) {
+        Ok((valid, _)) if valid > 0 => Ok(config),
+        _ => Err(Error::Io(format!(
+            "{}: no valid certificates found",
+            path.display()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{temp_dir, Response, Server};
+
+    fn settings(retry: RetryPolicy) -> HttpSettings {
+        HttpSettings {
+            timeout: Duration::from_secs(5),
+            user_agent: String::new(),
+            retry,
+            rate_limit: RateLimit::none(),
+            proxy: None,
+            ca_bundle: None,
+        }
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        assert_eq!(parse_retry_after(" 30 "), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn retry_after_as_date() {
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(120));
+        let wait = parse_retry_after(&date).unwrap();
+        assert!(wait > Duration::from_secs(100) && wait <= Duration::from_secs(120));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::from_secs(0))
+        );
+    }
+
+    #[test]
+    fn retry_after_invalid() {
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_delay() {
+        let policy = RetryPolicy::new(10, Duration::from_millis(100))
+            .max_delay(Duration::from_secs(1))
+            .jitter(false);
+        let delays = (0..6).map(|retries| policy.backoff(retries).as_millis());
+        assert_eq!(delays.collect::<Vec<_>>(), [100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn backoff_with_jitter() {
+        let policy = RetryPolicy::new(10, Duration::from_millis(100));
+        for retries in 0..4 {
+            let delay = Duration::from_millis(100 << retries);
+            let jittered = policy.backoff(retries);
+            assert!(jittered >= delay / 2 && jittered <= delay);
+        }
+    }
+
+    #[test]
+    fn deadline_stops_retries() {
+        let server = Server::new(vec![("/", Response::status(503))]);
+        let http = Http::new(settings(
+            RetryPolicy::new(3, Duration::from_secs(10)).deadline(Duration::from_secs(1)),
+        ));
+        let start = Instant::now();
+        assert_eq!(
+            http.get(&format!("{}/", server.url())).err(),
+            Some(Error::ServerError(503))
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn retries_honor_retry_after() {
+        let server = Server::new(vec![
+            ("/", Response::status(429).header("Retry-After", "1")),
+            ("/", Response::ok("")),
+        ]);
+        let http = Http::new(settings(
+            RetryPolicy::new(1, Duration::from_millis(1)).jitter(false),
+        ));
+        let start = Instant::now();
+        assert!(http.get(&format!("{}/", server.url())).is_ok());
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn token_bucket_allows_burst() {
+        let bucket = TokenBucket::new(RateLimit::new(10, Duration::from_secs(1)).burst(3));
+        let start = Instant::now();
+        for _ in 0..3 {
+            bucket.acquire();
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+        bucket.acquire();
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn token_bucket_unlimited() {
+        let bucket = TokenBucket::new(RateLimit::none());
+        let start = Instant::now();
+        for _ in 0..1000 {
+            bucket.acquire();
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn user_agent_with_contact() {
+        let version = env!("CARGO_PKG_VERSION");
+        assert_eq!(
+            user_agent(None),
+            format!(
+                "cargo-free/{} (+https://github.com/SirWindfield/cargo-free)",
+                version
+            )
+        );
+        assert_eq!(
+            user_agent(Some("me@example.com")),
+            format!(
+                "cargo-free/{} (+https://github.com/SirWindfield/cargo-free; me@example.com)",
+                version
+            )
+        );
+    }
+
+    #[test]
+    fn explicit_proxy_is_used_for_all_urls() {
+        let proxies = Proxies::new(Some("http://proxy:3128/")).unwrap();
+        let proxy = proxies.all.as_ref().unwrap();
+        for url in &["https://index.crates.io/se/rd/serde", "http://localhost/"] {
+            assert!(std::ptr::eq(proxies.for_url(url).unwrap(), proxy));
+        }
+    }
+
+    #[test]
+    fn proxies_by_scheme() {
+        let proxies = Proxies {
+            https: Some(parse_proxy("http://secure:3128").unwrap()),
+            http: Some(parse_proxy("http://plain:3128").unwrap()),
+            no_proxy: vec!["example.com".to_string(), "localhost".to_string()],
+            ..Proxies::default()
+        };
+        let https = proxies.https.as_ref().unwrap();
+        let http = proxies.http.as_ref().unwrap();
+        assert!(std::ptr::eq(
+            proxies.for_url("https://crates.io/api").unwrap(),
+            https
+        ));
+        assert!(std::ptr::eq(
+            proxies.for_url("http://crates.io/api").unwrap(),
+            http
+        ));
+        assert!(proxies.for_url("https://example.com/").is_none());
+        assert!(proxies.for_url("https://index.EXAMPLE.com:8080/").is_none());
+        assert!(proxies.for_url("http://localhost:1234/").is_none());
+        assert!(proxies.for_url("https://notexample.com/").is_some());
+        assert!(proxies.for_url("file:///index").is_none());
+    }
+
+    #[test]
+    fn no_proxy_wildcard() {
+        let proxies = Proxies {
+            https: Some(parse_proxy("http://secure:3128").unwrap()),
+            no_proxy: vec!["*".to_string()],
+            ..Proxies::default()
+        };
+        assert!(proxies.for_url("https://crates.io/").is_none());
+    }
+
+    #[test]
+    fn invalid_settings_fail_every_request() {
+        let server = Server::new(vec![]);
+        let url = format!("{}/", server.url());
+
+        let mut invalid_proxy = settings(RetryPolicy::none());
+        invalid_proxy.proxy = Some("http://proxy:port".to_string());
+        assert!(matches!(
+            Http::new(invalid_proxy).get(&url),
+            Err(Error::Transport(_))
+        ));
+
+        let ca_bundle = temp_dir("http-ca-bundle").join("ca.pem");
+        std::fs::write(&ca_bundle, "not a certificate").unwrap();
+        let mut invalid_ca_bundle = settings(RetryPolicy::none());
+        invalid_ca_bundle.ca_bundle = Some(ca_bundle);
+        assert!(matches!(
+            Http::new(invalid_ca_bundle).get(&url),
+            Err(Error::Io(_))
+        ));
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn sends_token() {
+        let server = Server::new(vec![("/", Response::ok(""))]);
+        let http = Http::new(settings(RetryPolicy::none()));
+        let url = format!("{}/", server.url());
+        http.get_authorized(&url, Some("secret")).unwrap();
+        http.get(&url).unwrap();
+        let requests = server.requests();
+        assert_eq!(requests[0].headers["authorization"], "secret");
+        assert!(!requests[1].headers.contains_key("authorization"));
+    }
+
+    #[test]
+    fn unauthorized() {
+        let server = Server::new(vec![
+            ("/401", Response::status(401)),
+            ("/403", Response::status(403)),
+        ]);
+        let http = Http::new(settings(RetryPolicy::none()));
+        for status in [401, 403] {
+            assert_eq!(
+                http.get(&format!("{}/{}", server.url(), status)).err(),
+                Some(Error::Unauthorized(status))
+            );
+        }
+    }
+}
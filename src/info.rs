@@ -0,0 +1,60 @@
+use std::time::SystemTime;
+
+/// The metadata of an existing crate, as returned by
+/// [`Checker::lookup`](crate::Checker::lookup).
+///
+/// Which fields are known depends on the backend: the crates.io API knows
+/// all of them, while registry indices only know versions and local data
+/// often little more than the name.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct CrateInfo {
+    /// The crate's name, as spelled by its owners.
+    pub name: String,
+
+    /// The logins of the users and teams owning the crate, e.g. `dtolnay` or
+    /// `github:serde-rs:publish`.
+    pub owners: Option<Vec<String>>,
+
+    /// The description from the crate's manifest.
+    pub description: Option<String>,
+
+    /// The repository URL from the crate's manifest.
+    pub repository: Option<String>,
+
+    /// The time the crate was first published.
+    pub created_at: Option<SystemTime>,
+
+    /// The time the crate was last updated, e.g. by publishing a version.
+    pub updated_at: Option<SystemTime>,
+
+    /// The time the latest version was published.
+    pub published_at: Option<SystemTime>,
+
+    /// The maintenance status declared via the `maintenance` badge of the
+    /// manifest, e.g. `deprecated` or `looking-for-maintainer`.
+    pub maintenance: Option<String>,
+
+    /// The highest version that is not yanked.
+    pub max_version: Option<String>,
+
+    /// The total number of downloads.
+    pub downloads: Option<u64>,
+
+    /// The number of downloads in the last 90 days.
+    pub recent_downloads: Option<u64>,
+
+    /// Whether all published versions are yanked.
+    pub yanked: Option<bool>,
+}
+
+impl CrateInfo {
+    /// Creates the metadata of the crate `name`, with all other fields
+    /// unknown.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+}
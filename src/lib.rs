@@ -1,16 +1,117 @@
-use std::{fmt, fmt::Formatter, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt,
+    fmt::Formatter,
+    path::Path,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
 use thiserror::Error;
 
-const DEFAULT_TIMEOUT_SECONDS: u64 = 5;
+mod backend;
+mod cache;
+mod checker;
+mod config;
+mod confusable;
+mod credentials;
+mod dormancy;
+mod dump;
+mod http;
+mod info;
+mod name;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+mod similar;
+mod snapshot;
+mod squat;
+
+pub use crate::{
+    backend::{Backend, BackendKind, Lookup, MockBackend, Status},
+    cache::{cache_stats, clear_cache, default_cache_path, CacheStats},
+    checker::{Check, Checker, CheckerBuilder, CrateSummary},
+    confusable::{ascii_skeleton, confusables, Confusable},
+    dormancy::{Dormancy, DownloadTrend},
+    dump::{default_db_dump_path, import_db_dump},
+    http::{default_user_agent, RateLimit, RetryPolicy},
+    info::CrateInfo,
+    name::{canonical_name, is_reserved, validate_name, InvalidReason, MAX_NAME_LENGTH},
+    similar::{PopularCrates, Similarity, SimilarityKind},
+    snapshot::{default_snapshot_path, Snapshot},
+    squat::{SquatReport, SquatSignal},
+};
 
 /// The crate's error type.
-#[derive(Debug, Error, Eq, PartialEq)]
+#[derive(Clone, Debug, Error, Eq, Hash, PartialEq)]
 pub enum Error {
     #[error("crate name is empty")]
     EmptyCrateName,
 
+    #[error("crate name is invalid, {0}")]
+    InvalidCrateName(InvalidReason),
+
+    #[error("crate name is reserved")]
+    ReservedCrateName,
+
     #[error("API request to crates.io timed out after {0:?}")]
     NetworkTimeout(Duration),
+
+    #[error("failed to connect to the registry: {0}")]
+    Transport(String),
+
+    #[error("rate limited by the registry{}", retry_after_suffix(.retry_after))]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("registry responded with server error {0}")]
+    ServerError(u16),
+
+    #[error("registry sent an invalid response: {0}")]
+    InvalidResponse(String),
+
+    #[error("failed to read local data: {0}")]
+    Io(String),
+
+    #[error(
+        "no index configured for registry `{0}`, add it to `[registries]` in .cargo/config.toml"
+    )]
+    UnknownRegistry(String),
+
+    #[error("registry denied access with status {0}, check the configured token")]
+    Unauthorized(u16),
+
+    #[error("failed to obtain a token for {0}")]
+    Credential(String),
+
+    #[error("invalid source replacement: {0}")]
+    SourceReplacement(String),
+
+    #[error("could not determine whether the name is in use")]
+    Unresolved,
+}
+
+impl Error {
+    /// Returns `true` if the error is likely to go away when the request is
+    /// retried later on, e.g. timeouts, rate limits or server outages.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Error::NetworkTimeout(_)
+                | Error::Transport(_)
+                | Error::RateLimited { .. }
+                | Error::ServerError(_)
+        )
+    }
+
+    /// Creates an `Error::Io` for a failed operation on `path`.
+    pub(crate) fn io(path: &Path, e: impl fmt::Display) -> Self {
+        Error::Io(format!("{}: {}", path.display(), e))
+    }
+}
+
+fn retry_after_suffix(retry_after: &Option<Duration>) -> String {
+    match retry_after {
+        Some(retry_after) => format!(", retry after {:?}", retry_after),
+        None => String::new(),
+    }
 }
 
 /// The availability status of a crate name.
@@ -24,6 +125,14 @@ pub enum Availability {
 
     /// The crate name can't be resolved.
     Unknown,
+
+    /// The crate name violates the naming rules of crates.io and can never be
+    /// published.
+    Invalid(InvalidReason),
+
+    /// The crate name is reserved by crates.io and can't be published,
+    /// although no crate uses it.
+    Reserved,
 }
 
 impl fmt::Display for Availability {
@@ -32,6 +141,8 @@ impl fmt::Display for Availability {
             Availability::Available => write!(f, "available"),
             Availability::Unavailable => write!(f, "unavailable"),
             Availability::Unknown => write!(f, "unknown"),
+            Availability::Invalid(_) => write!(f, "invalid"),
+            Availability::Reserved => write!(f, "reserved"),
         }
     }
 }
@@ -45,18 +156,23 @@ impl fmt::Display for Availability {
 /// # Returns
 ///
 /// `Ok(Availability)` if the name could be resolved. If the crate name is
-/// empty, `Err(Error::EmptyCrateName)` gets returned. Returns
+/// empty, `Err(Error::EmptyCrateName)` gets returned. Names violating the
+/// crates.io naming rules result in `Ok(Availability::Invalid)` without any
+/// network request being made, the same goes for reserved names and
+/// `Ok(Availability::Reserved)`. Network failures are
+/// reported using the remaining `Error` variants, e.g.
 /// `Err(Error::NetworkTimeout)` if a timeout occurred.
 ///
 /// # Note
 ///
-/// The needed network request will timeout after five seconds.
+/// The needed network request will timeout after five seconds. All calls
+/// share one default [`Checker`].
 pub fn check_availability(name: impl AsRef<str>) -> Result<Availability, Error> {
-    check_availability_with_timeout(name, Duration::from_secs(DEFAULT_TIMEOUT_SECONDS))
+    default_checker().check_availability(name)
 }
 
 /// Checks the availability for a given crate name. Stops after the given
-/// timeout duration and returns `Availability::Unknown`.
+/// timeout duration and returns `Error::NetworkTimeout`.
 ///
 /// # Arguments
 ///
@@ -67,24 +183,81 @@ pub fn check_availability(name: impl AsRef<str>) -> Result<Availability, Error>
 /// # Returns
 ///
 /// `Ok(Availability)` if the name could be resolved. If the crate name is
-/// empty, `Err(Error::EmptyCrateName)` gets returned. Returns
+/// empty, `Err(Error::EmptyCrateName)` gets returned. Names violating the
+/// crates.io naming rules result in `Ok(Availability::Invalid)` without any
+/// network request being made, the same goes for reserved names and
+/// `Ok(Availability::Reserved)`. Network failures are
+/// reported using the remaining `Error` variants, e.g.
 /// `Err(Error::NetworkTimeout)` if a timeout occurred.
+///
+/// # Note
+///
+/// All calls using the same timeout share one [`Checker`], and thus its
+/// rate limit.
 pub fn check_availability_with_timeout(
     name: impl AsRef<str>,
     timeout: Duration,
 ) -> Result<Availability, Error> {
-    let name = name.as_ref();
-    if name.is_empty() {
-        return Err(Error::EmptyCrateName);
+    checker_with_timeout(timeout).check_availability(name)
+}
+
+/// Looks up the metadata of the crate using a given name, e.g. its owners,
+/// latest version and download counts.
+///
+/// # Returns
+///
+/// `Ok(Some(CrateInfo))` if a crate uses the name or one of its variants,
+/// `Ok(None)` if the name is free. Names violating the crates.io naming rules
+/// result in `Err(Error::InvalidCrateName)` without any network request being
+/// made, the same goes for reserved names and `Err(Error::ReservedCrateName)`.
+/// Failures are reported like by [`check_availability`].
+///
+/// # Note
+///
+/// All calls share one default [`Checker`].
+pub fn lookup(name: impl AsRef<str>) -> Result<Option<CrateInfo>, Error> {
+    default_checker().lookup(name)
+}
+
+/// Returns the checker shared by the free functions.
+pub(crate) fn default_checker() -> &'static Checker {
+    static CHECKER: OnceLock<Checker> = OnceLock::new();
+    CHECKER.get_or_init(Checker::new)
+}
+
+/// Returns the checker shared by all calls of
+/// [`check_availability_with_timeout`] using `timeout`.
+fn checker_with_timeout(timeout: Duration) -> Arc<Checker> {
+    static CHECKERS: OnceLock<Mutex<HashMap<Duration, Arc<Checker>>>> = OnceLock::new();
+    let checkers = CHECKERS.get_or_init(Default::default);
+    if let Some(checker) = checkers.lock().unwrap().get(&timeout) {
+        return Arc::clone(checker);
     }
 
-    let url = format!("https://crates.io/api/v1/crates/{}", name);
-    let resp = ureq::get(&url).timeout(timeout).call();
-    let availability = match resp.status() {
-        200 => Availability::Unavailable,
-        404 => Availability::Available,
-        408 => return Err(Error::NetworkTimeout(timeout)),
-        _ => Availability::Unknown,
-    };
-    Ok(availability)
+    // Building reads Cargo's configuration, calls using other timeouts
+    // shouldn't wait for it. If another call built a checker meanwhile, its
+    // checker is kept.
+    let checker = Arc::new(Checker::builder().timeout(timeout).build());
+    Arc::clone(checkers.lock().unwrap().entry(timeout).or_insert(checker))
+}
+
+#[cfg(test)]
+mod testing;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkers_are_shared_per_timeout() {
+        let checker = checker_with_timeout(Duration::from_secs(1));
+        assert!(Arc::ptr_eq(
+            &checker,
+            &checker_with_timeout(Duration::from_secs(1))
+        ));
+        assert!(!Arc::ptr_eq(
+            &checker,
+            &checker_with_timeout(Duration::from_secs(2))
+        ));
+    }
 }
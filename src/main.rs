@@ -1,10 +1,29 @@
-use cargo_free::{check_availability, Availability, Error};
+use cargo_free::{
+    ascii_skeleton, cache_stats, clear_cache, default_cache_path, default_db_dump_path,
+    default_snapshot_path, import_db_dump, Availability, BackendKind, Check, Checker, CrateInfo,
+    Dormancy, DownloadTrend, Error, PopularCrates, RetryPolicy, Similarity, SimilarityKind,
+    Snapshot, SquatReport,
+};
 use clap::{AppSettings, Clap};
-use serde_json::json;
-use std::process::exit;
-use terminal_log_symbols::colored::{ERROR_SYMBOL, SUCCESS_SYMBOL, UNKNOWN_SYMBOL};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::{json, Value};
+use std::{
+    collections::HashSet,
+    env,
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+    process::exit,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use terminal_log_symbols::colored::{
+    ERROR_SYMBOL, INFO_SYMBOL, SUCCESS_SYMBOL, UNKNOWN_SYMBOL, WARNING_SYMBOL,
+};
 use terminal_spinners::{SpinnerBuilder, DOTS};
 
+/// The number of most downloaded crates names are compared against, see
+/// `--popular`.
+const DEFAULT_POPULAR_CRATES: usize = 1000;
+
 /// XXX: There is no first-class support for cargo subcommands. This is
 /// basically the "official" workaround.
 #[derive(Clap, Debug)]
@@ -26,89 +45,1335 @@ enum Cli {
 #[derive(Clap, Debug)]
 struct FreeArgs {
     /// Output result as json object.
+    #[cfg(feature = "json")]
     #[clap(long, short)]
     json: bool,
 
-    /// The crate name to check for availability.
+    /// Print additional information, e.g. retried requests.
+    #[clap(long, short)]
+    verbose: bool,
+
+    /// The backend used to resolve crate names.
+    #[clap(
+        long,
+        value_name = "BACKEND",
+        possible_values = &["api", "sparse", "git", "db-dump", "snapshot"],
+        default_value = "api"
+    )]
+    backend: String,
+
+    /// The base URL of the registry API or index to query.
+    #[clap(long, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// Check names against an alternative registry configured in
+    /// `.cargo/config.toml` instead of crates.io. Repeat to check all names
+    /// against several registries, `crates-io` refers to crates.io. Exits with
+    /// 2 if a name is not free in every registry.
+    #[clap(
+        long,
+        value_name = "NAME",
+        number_of_values = 1,
+        conflicts_with_all = &["backend", "base-url"]
+    )]
+    registry: Vec<String>,
+
+    /// Answer from Cargo's local index cache without accessing the network.
+    /// Also enabled by setting `CARGO_NET_OFFLINE=true`.
+    #[clap(long)]
+    offline: bool,
+
+    /// The maximum number of names to check concurrently.
+    #[clap(long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Do not read or write the result cache.
+    #[clap(long)]
+    no_cache: bool,
+
+    /// Ignore cached results, but update the cache with the fetched ones.
+    #[clap(long)]
+    refresh: bool,
+
+    /// How long cached results are reused, e.g. `12h` or `7days`.
+    #[clap(long, value_name = "DURATION", default_value = "1day", parse(try_from_str = humantime::parse_duration))]
+    cache_ttl: Duration,
+
+    /// Warn about names resembling one of the N most downloaded crates of the
+    /// imported database dump, see `cargo free index import`. Defaults to
+    /// 1000 for names passed as arguments and 0, disabling the warnings, for
+    /// names read from stdin.
+    #[clap(long, value_name = "N")]
+    popular: Option<usize>,
+
+    /// The crate name to check for availability. Pass `-` to read names from
+    /// stdin, one per line. Names of subcommands, e.g. `info`, are checked
+    /// when passed after `--`.
     names: Vec<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clap, Debug)]
+enum Command {
+    /// Show who owns a taken name and whether the crate is still maintained.
+    Info {
+        /// Download the latest package and check it for code when rating
+        /// whether the name is squatted.
+        #[clap(long)]
+        inspect: bool,
+
+        /// The crate name to look up.
+        name: String,
+    },
+
+    /// Show the owners of a taken name and draft a request to transfer it.
+    Adopt {
+        /// Your crates.io login, used in the drafted request.
+        #[clap(long, value_name = "LOGIN")]
+        login: Option<String>,
+
+        /// The crate name to adopt.
+        name: String,
+    },
+
+    /// Manage the local crates.io database dump store and name snapshot.
+    #[clap(setting(AppSettings::SubcommandRequiredElseHelp))]
+    Index(IndexCommand),
+
+    /// Manage the cache of previous results.
+    #[clap(setting(AppSettings::SubcommandRequiredElseHelp))]
+    Cache(CacheCommand),
+}
+
+#[derive(Clap, Debug)]
+enum CacheCommand {
+    /// Remove all cached results.
+    Clear,
+
+    /// Show the number of cached results.
+    Stats,
+}
+
+#[derive(Clap, Debug)]
+enum IndexCommand {
+    /// Import a crates.io database dump (`db-dump.tar.gz`) for use with
+    /// `--backend db-dump`.
+    Import {
+        /// The path of the downloaded database dump.
+        archive: PathBuf,
+    },
+
+    /// Build the name snapshot used by `--backend snapshot`, from the
+    /// imported database dump or a registry index checkout.
+    Snapshot {
+        /// Build from a checkout of a registry index instead of the imported
+        /// database dump.
+        #[clap(long, value_name = "DIR")]
+        from_index: Option<PathBuf>,
+    },
+
+    /// Search the name snapshot for existing crates.
+    Search {
+        /// Match all names starting with the query.
+        #[clap(long)]
+        prefix: bool,
+
+        /// Match names within the given edit distance of the query.
+        #[clap(long, value_name = "N", default_value = "0")]
+        distance: u32,
+
+        /// The name to search for.
+        query: String,
+    },
+}
+
+impl FreeArgs {
+    fn names(&self) -> io::Result<Vec<String>> {
+        if self.names != ["-"] {
+            return Ok(self.names.clone());
+        }
+
+        io::stdin()
+            .lock()
+            .lines()
+            .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|line| line.map(|line| line.trim().to_string()))
+            .collect()
+    }
+
+    /// Returns `true` if names are answered from Cargo's local index cache.
+    /// Only backends accessing the network are replaced by it, the db dump
+    /// and snapshot backends are local already.
+    fn offline(&self) -> bool {
+        let offline = self.offline
+            || env::var("CARGO_NET_OFFLINE").is_ok_and(|value| value == "true" || value == "1");
+        offline && matches!(self.backend.as_str(), "api" | "sparse" | "git")
+    }
+
+    /// Returns `true` if names are checked against an alternative registry.
+    fn alternative_registry(&self) -> bool {
+        self.registry.iter().any(|registry| registry != "crates-io")
+    }
+
+    fn json(&self) -> bool {
+        #[cfg(feature = "json")]
+        {
+            self.json
+        }
+        #[cfg(not(feature = "json"))]
+        {
+            false
+        }
+    }
+}
+
+/// Prints log messages of the library to stderr, omitting those of its
+/// dependencies.
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info && metadata.target().starts_with("cargo_free")
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let symbol = match record.level() {
+                Level::Error => ERROR_SYMBOL,
+                Level::Warn => WARNING_SYMBOL,
+                _ => INFO_SYMBOL,
+            };
+            eprintln!("{} {}", symbol, record.args());
+        }
+    }
+
+    fn flush(&self) {}
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
-    match cli {
-        Cli::Free(args) => {
-            // The spinner should only be shown if the user does not want json, as the
-            // spinner will interfere with piping otherwise.
-            let mut handle = None;
-            if !args.json {
-                handle = Some(
-                    SpinnerBuilder::new()
-                        .spinner(&DOTS)
-                        .text("Fetching metadata from crates.io ...")
-                        .start(),
-                );
-            }
+    let Cli::Free(args) = Cli::parse();
+    if args.verbose && log::set_logger(&Logger).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
 
-            // Calculate the maximum character length of all crate names supplied alongside
-            // their availability.
-            let mut max_length_crate_name = 0;
-            let availabilities = args
-                .names
-                .iter()
-                .map(|crate_name| {
-                    let crate_name_length = crate_name.len();
-                    if crate_name_length > max_length_crate_name {
-                        max_length_crate_name = crate_name_length;
-                    }
+    match &args.command {
+        Some(Command::Index(IndexCommand::Import { archive })) => import(archive),
+        Some(Command::Index(IndexCommand::Snapshot { from_index })) => {
+            snapshot(from_index.as_deref())
+        }
+        Some(Command::Index(IndexCommand::Search {
+            prefix,
+            distance,
+            query,
+        })) => search(query, *prefix, *distance),
+        Some(Command::Info { inspect, name }) => info(&args, name, *inspect),
+        Some(Command::Adopt { login, name }) => adopt(&args, name, login.as_deref()),
+        Some(Command::Cache(CacheCommand::Clear)) => cache_clear(),
+        Some(Command::Cache(CacheCommand::Stats)) => cache_info(args.cache_ttl),
+        None => check(&args),
+    }
+}
 
-                    (crate_name, check_availability(&crate_name))
+fn check(args: &FreeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.alternative_registry() && args.offline() {
+        return Err("alternative registries can't be checked offline".into());
+    }
+
+    // The spinner should only be shown if the user does not want json, as the
+    // spinner will interfere with piping otherwise. The same goes for verbose
+    // output.
+    let mut handle = None;
+    if !args.json() && !args.verbose {
+        handle = Some(
+            SpinnerBuilder::new()
+                .spinner(&DOTS)
+                .text(match args.registry.as_slice() {
+                    _ if args.offline() => "Reading local index cache ...".to_string(),
+                    [] => "Fetching metadata from crates.io ...".to_string(),
+                    registries => format!("Fetching metadata from {} ...", registries.join(", ")),
                 })
-                .collect::<Vec<_>>();
-            // Check if the list is empty (user did not supply any crate names).
-            if availabilities.is_empty() {
-                if let Some(handle) = handle {
-                    handle.text("No crate names supplied!");
-                    handle.error();
-                } else {
-                    eprintln!("No crate names supplied!");
+                .start(),
+        );
+    }
+
+    let names = args.names()?;
+    // Check if the list is empty (user did not supply any crate names).
+    if names.is_empty() {
+        if let Some(handle) = handle {
+            handle.text("No crate names supplied!");
+            handle.error();
+        } else {
+            eprintln!("No crate names supplied!");
+        }
+        exit(1);
+    }
+
+    if args.registry.len() > 1 {
+        let results = args
+            .registry
+            .iter()
+            .map(|registry| checker(args, Some(registry)).check_many(&names))
+            .collect::<Vec<_>>();
+        if let Some(handle) = handle {
+            handle.stop_and_clear();
+        }
+        return check_matrix(args, &names, results);
+    }
+    let popular = popular_crates(args);
+    let similar = |crate_name: &str| {
+        popular
+            .as_ref()
+            .map(|popular| popular.similar_to(crate_name))
+            .unwrap_or_default()
+    };
+
+    let checker = checker(args, args.registry.first().map(String::as_str));
+    let availabilities = names
+        .iter()
+        .zip(checker.check_many(&names))
+        .collect::<Vec<_>>();
+    if let Some(handle) = handle {
+        handle.stop_and_clear();
+    }
+
+    let failed = availabilities
+        .iter()
+        .any(|(_, available)| available.is_err());
+    if args.json() {
+        let objects = availabilities
+            .into_iter()
+            .map(|(crate_name, available)| {
+                let mut object = match available {
+                    Ok(check) => check_to_json(check),
+                    Err(e) => json!({
+                        "crate": crate_name,
+                        "error": error_to_json(&e),
+                    }),
+                };
+                let similar = similar(crate_name);
+                if !similar.is_empty() {
+                    object["similar"] = similar_to_json(&similar);
                 }
-                exit(1);
-            }
+                object
+            })
+            .collect::<Vec<_>>();
 
-            if let Some(handle) = handle {
-                handle.stop_and_clear();
-            }
+        println!("{}", json!(objects));
+    } else {
+        print(availabilities, similar);
+    }
+
+    // Signal scripts that at least one name could not be checked.
+    if failed {
+        exit(1);
+    }
+
+    Ok(())
+}
+
+/// Reports the results of checking `names` against multiple registries,
+/// `results` contains one list of results per registry.
+fn check_matrix(
+    args: &FreeArgs,
+    names: &[String],
+    results: Vec<Vec<Result<Check, Error>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let popular = popular_crates(args);
+    let similar = |crate_name: &str| {
+        popular
+            .as_ref()
+            .map(|popular| popular.similar_to(crate_name))
+            .unwrap_or_default()
+    };
+
+    if args.json() {
+        println!(
+            "{}",
+            matrix_to_json(&args.registry, names, &results, similar)
+        );
+    } else {
+        print_matrix(&args.registry, names, &results);
+        for crate_name in names {
+            print_similar(crate_name, &similar(crate_name));
+        }
+    }
+
+    match matrix_exit_code(names, &results) {
+        0 => Ok(()),
+        code => exit(code),
+    }
+}
+
+/// Returns `true` if the `i`th name is available in every registry.
+fn free_everywhere(results: &[Vec<Result<Check, Error>>], i: usize) -> bool {
+    results.iter().all(
+        |checks| matches!(&checks[i], Ok(check) if check.availability == Availability::Available),
+    )
+}
 
-            if args.json {
-                let mut objects = Vec::with_capacity(availabilities.len());
-                for (crate_name, available) in availabilities {
-                    if let Ok(available) = available {
-                        objects.push(json!({
+/// Returns the exit code signaling scripts that at least one name could not
+/// be checked (1), or is not free in every registry (2).
+fn matrix_exit_code(names: &[String], results: &[Vec<Result<Check, Error>>]) -> i32 {
+    if results.iter().flatten().any(Result::is_err) {
+        1
+    } else if !(0..names.len()).all(|i| free_everywhere(results, i)) {
+        2
+    } else {
+        0
+    }
+}
+
+/// Returns one object per name, holding the result of each registry keyed
+/// by the registry's name.
+fn matrix_to_json(
+    registries: &[String],
+    names: &[String],
+    results: &[Vec<Result<Check, Error>>],
+    similar: impl Fn(&str) -> Vec<Similarity>,
+) -> Value {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, crate_name)| {
+            let objects = registries
+                .iter()
+                .zip(results)
+                .map(|(registry, checks)| {
+                    let object = match &checks[i] {
+                        Ok(check) => check_to_json(check.clone()),
+                        Err(e) => json!({
                             "crate": crate_name,
-                            "availability": available.to_string(),
-                        }));
-                    }
-                }
+                            "error": error_to_json(e),
+                        }),
+                    };
+                    (registry.clone(), object)
+                })
+                .collect::<serde_json::Map<_, _>>();
+            let mut object = json!({
+                "crate": crate_name,
+                "free_everywhere": free_everywhere(results, i),
+                "registries": objects,
+            });
+            let similar = similar(crate_name);
+            if !similar.is_empty() {
+                object["similar"] = similar_to_json(&similar);
+            }
+            object
+        })
+        .collect()
+}
 
-                println!("{}", json!(objects));
-            } else {
-                print(availabilities);
+fn info(args: &FreeArgs, name: &str, inspect: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if args.registry.len() > 1 {
+        return Err("`info` looks up a name in a single registry".into());
+    }
+
+    let mut handle = None;
+    if !args.json() && !args.verbose {
+        handle = Some(
+            SpinnerBuilder::new()
+                .spinner(&DOTS)
+                .text(format!("Fetching metadata of {} ...", name))
+                .start(),
+        );
+    }
+    let checker = checker(args, args.registry.first().map(String::as_str));
+    let result = checker.lookup(name);
+    let report = match &result {
+        Ok(Some(info)) if inspect => Some(checker.squat_report(info).unwrap_or_else(|e| {
+            log::warn!("failed to inspect the package of {}: {}", info.name, e);
+            info.squat_report()
+        })),
+        Ok(Some(info)) => Some(info.squat_report()),
+        _ => None,
+    };
+    if let Some(handle) = handle {
+        handle.stop_and_clear();
+    }
+
+    if args.json() {
+        let object = match (&result, report) {
+            (Ok(Some(info)), Some(report)) => {
+                let mut object = info_to_json(name, info);
+                object["squat"] = json!({
+                    "score": report.score,
+                    "likely": report.is_likely(),
+                    "signals": report
+                        .signals
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>(),
+                });
+                object
             }
+            (Ok(Some(info)), None) => info_to_json(name, info),
+            (Ok(None), _) => json!({
+                "crate": name,
+                "taken": false,
+            }),
+            (Err(e), _) => json!({
+                "crate": name,
+                "error": error_to_json(e),
+            }),
+        };
+        println!("{}", object);
+    } else {
+        match &result {
+            Ok(Some(info)) => print_info(info, report.as_ref()),
+            Ok(None) => println!("{} {}: no crate uses this name", SUCCESS_SYMBOL, name),
+            Err(e) => println!("{} {}: {}", WARNING_SYMBOL, name, e),
+        }
+    }
+
+    if result.is_err() {
+        exit(1);
+    }
+    Ok(())
+}
+
+fn adopt(
+    args: &FreeArgs,
+    name: &str,
+    login: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.registry.len() > 1 {
+        return Err("`adopt` looks up a name in a single registry".into());
+    }
+
+    let mut handle = None;
+    if !args.json() && !args.verbose {
+        handle = Some(
+            SpinnerBuilder::new()
+                .spinner(&DOTS)
+                .text(format!("Fetching metadata of {} ...", name))
+                .start(),
+        );
+    }
+    let result = checker(args, args.registry.first().map(String::as_str)).lookup(name);
+    if let Some(handle) = handle {
+        handle.stop_and_clear();
+    }
+
+    let info = match result {
+        Ok(Some(info)) => info,
+        Ok(None) if args.json() => {
+            println!("{}", json!({ "crate": name, "taken": false }));
+            return Ok(());
+        }
+        Ok(None) => {
+            println!("{} {}: no crate uses this name", SUCCESS_SYMBOL, name);
+            return Ok(());
+        }
+        Err(e) if args.json() => {
+            println!("{}", json!({ "crate": name, "error": error_to_json(&e) }));
+            exit(1);
+        }
+        Err(e) => {
+            println!("{} {}: {}", WARNING_SYMBOL, name, e);
+            exit(1);
+        }
+    };
+    let dormancy = info.dormancy();
+    let owners = info.owners.clone().unwrap_or_default();
+    let message = transfer_request(&info, &dormancy, login);
+
+    if args.json() {
+        let mut object = info_to_json(name, &info);
+        object["contacts"] = json!(owners
+            .iter()
+            .map(|owner| json!({ "login": owner, "url": owner_url(owner) }))
+            .collect::<Vec<_>>());
+        object["message"] = json!(message);
+        println!("{}", object);
+        return Ok(());
+    }
+
+    println!("{} {}", ERROR_SYMBOL, info.name);
+    if owners.is_empty() {
+        println!(
+            "  The owners are unknown, look them up on https://crates.io/crates/{}",
+            info.name
+        );
+    }
+    for owner in &owners {
+        println!("  {:<24} {}", owner, owner_url(owner));
+    }
+    if let Some(repository) = &info.repository {
+        println!("  {:<24} {}", "Repository", repository);
+    }
+    match activity(&dormancy) {
+        Some(activity) if dormancy.is_dormant() => {
+            println!("{} The crate looks dormant: {}", INFO_SYMBOL, activity)
         }
+        Some(activity) => println!(
+            "{} The crate does not look dormant ({}), the owners may decline",
+            WARNING_SYMBOL, activity
+        ),
+        None => {}
     }
 
+    println!();
+    println!("{}", message);
+    println!();
+    println!(
+        "{} If the owners can't be reached, see the crates.io policies on name transfers: https://crates.io/policies",
+        INFO_SYMBOL
+    );
     Ok(())
 }
 
-fn print(availabilities: Vec<(&String, Result<Availability, Error>)>) {
+/// Returns the crates.io profile URL of an owner, e.g. `alice` or
+/// `github:org:team`.
+fn owner_url(owner: &str) -> String {
+    if owner.contains(':') {
+        format!("https://crates.io/teams/{}", owner)
+    } else {
+        format!("https://crates.io/users/{}", owner)
+    }
+}
+
+/// Drafts a message asking the owners of `info` to transfer the crate.
+fn transfer_request(info: &CrateInfo, dormancy: &Dormancy, login: Option<&str>) -> String {
+    // Teams can't be addressed personally.
+    let users = info
+        .owners
+        .iter()
+        .flatten()
+        .filter(|owner| !owner.contains(':'))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let greeting = if users.is_empty() {
+        "Hi,".to_string()
+    } else {
+        format!("Hi {},", users.join(", "))
+    };
+
+    let mut facts = Vec::new();
+    if let Some(last_published) = dormancy.last_published {
+        match &info.max_version {
+            Some(version) => facts.push(format!(
+                "its latest version {} was published on {}",
+                version,
+                date(last_published)
+            )),
+            None => facts.push(format!("it was last updated on {}", date(last_published))),
+        }
+    }
+    if let Some(recent) = info.recent_downloads {
+        facts.push(format!(
+            "it was downloaded {} times in the last 90 days",
+            recent
+        ));
+    }
+    if dormancy.unmaintained {
+        facts.push("it is marked as unmaintained".to_string());
+    }
+    let state = if dormancy.is_dormant() && !facts.is_empty() {
+        format!(
+            " It looks like the crate is no longer actively developed: {}.",
+            facts.join(", ")
+        )
+    } else {
+        String::new()
+    };
+
+    format!(
+        "Subject: Transferring the `{name}` crate
+
+{greeting}
+
+I'm working on a project I'd like to publish on crates.io as `{name}`, which is currently taken by your crate.{state}
+
+Would you be willing to transfer the name to me? If so, you can add me as an owner with
+
+    cargo owner --add {login} {name}
+
+and I'll take care of the rest. I'm happy to keep the existing versions available and to mention your work in the new crate's documentation. If you are still working on the crate or have other plans for the name, just let me know and I'll look for another one.
+
+Thank you for your time!",
+        name = info.name,
+        greeting = greeting,
+        state = state,
+        login = login.unwrap_or("<your-login>"),
+    )
+}
+
+fn import(archive: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let store = default_db_dump_path().ok_or("failed to determine the data directory")?;
+    let handle = SpinnerBuilder::new()
+        .spinner(&DOTS)
+        .text(format!("Importing {} ...", archive.display()))
+        .start();
+
+    match import_db_dump(archive, &store) {
+        Ok(count) => {
+            handle.text(format!(
+                "Imported {} crates into {}",
+                count,
+                store.display()
+            ));
+            handle.done();
+            Ok(())
+        }
+        Err(e) => {
+            handle.text(e.to_string());
+            handle.error();
+            exit(1);
+        }
+    }
+}
+
+fn snapshot(from_index: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = default_snapshot_path().ok_or("failed to determine the data directory")?;
+    let snapshot = match from_index {
+        Some(dir) => Snapshot::from_index_dir(dir)?,
+        None => {
+            let store = default_db_dump_path().ok_or("failed to determine the data directory")?;
+            Snapshot::from_db_dump(store)?
+        }
+    };
+    snapshot.save(&path)?;
+
+    println!(
+        "{} Wrote snapshot of {} crates to {}",
+        SUCCESS_SYMBOL,
+        snapshot.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn search(query: &str, prefix: bool, distance: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let path = default_snapshot_path().ok_or("failed to determine the data directory")?;
+    let snapshot = Snapshot::load(path)?;
+    let names = match (prefix, distance) {
+        (true, 0) => snapshot.prefix(query),
+        (true, distance) => snapshot.fuzzy_prefix(query, distance)?,
+        (false, 0) => snapshot.get(query).into_iter().collect(),
+        (false, distance) => snapshot.fuzzy(query, distance)?,
+    };
+
+    for name in names {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+fn cache_clear() -> Result<(), Box<dyn std::error::Error>> {
+    let path = default_cache_path().ok_or("failed to determine the cache directory")?;
+    clear_cache(&path)?;
+    println!("{} Cleared the result cache", SUCCESS_SYMBOL);
+    Ok(())
+}
+
+fn cache_info(ttl: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let path = default_cache_path().ok_or("failed to determine the cache directory")?;
+    let stats = cache_stats(&path, ttl)?;
+    println!("Location: {}", path.display());
+    println!(
+        "Entries:  {} ({} older than {})",
+        stats.entries,
+        stats.expired,
+        humantime::format_duration(ttl)
+    );
+    println!("Size:     {} bytes", stats.size);
+    Ok(())
+}
+
+/// Loads the most downloaded crates from the imported database dump. Returns
+/// `None` if the warnings are disabled or no dump was imported.
+fn popular_crates(args: &FreeArgs) -> Option<PopularCrates> {
+    // Bulk checks read names from stdin and shouldn't pay for the
+    // comparison unless asked to.
+    let top = match args.popular {
+        Some(top) => top,
+        None if args.names == ["-"] => 0,
+        None => DEFAULT_POPULAR_CRATES,
+    };
+    if top == 0 {
+        return None;
+    }
+    let path = default_db_dump_path().filter(|path| path.is_file())?;
+
+    match PopularCrates::from_db_dump(path, top) {
+        Ok(popular) => Some(popular),
+        Err(e) => {
+            eprintln!("{} skipping the typosquatting check: {}", WARNING_SYMBOL, e);
+            None
+        }
+    }
+}
+
+fn checker(args: &FreeArgs, registry: Option<&str>) -> Checker {
+    let backend = match args.backend.as_str() {
+        _ if args.offline() => BackendKind::Offline,
+        "sparse" => BackendKind::SparseIndex,
+        "git" => BackendKind::GitIndex,
+        "db-dump" => BackendKind::DbDump,
+        "snapshot" => BackendKind::Snapshot,
+        _ => BackendKind::Api,
+    };
+    let mut builder = Checker::builder()
+        .backend(backend)
+        .retry_policy(RetryPolicy::from_cargo_config());
+    if let Some(base_url) = &args.base_url {
+        builder = builder.base_url(base_url);
+    }
+    if let Some(registry) = registry {
+        builder = builder.registry(registry);
+    }
+    if let Some(jobs) = args.jobs {
+        builder = builder.jobs(jobs);
+    }
+    if !args.no_cache {
+        builder = builder.cache(args.cache_ttl).refresh_cache(args.refresh);
+    }
+
+    builder.build()
+}
+
+fn print(
+    availabilities: Vec<(&String, Result<Check, Error>)>,
+    similar: impl Fn(&str) -> Vec<Similarity>,
+) {
     for (crate_name, available) in availabilities {
-        if let Ok(available) = available {
-            let emoji = match available {
-                Availability::Available => SUCCESS_SYMBOL,
-                Availability::Unavailable => ERROR_SYMBOL,
-                Availability::Unknown => UNKNOWN_SYMBOL,
+        match available {
+            Ok(check) => print_check(crate_name, check, Vec::new(), ""),
+            Err(e) => println!("{} {}: {}", WARNING_SYMBOL, crate_name, e),
+        }
+        print_similar(crate_name, &similar(crate_name));
+    }
+}
+
+/// Prints the result of checking `crate_name`, starting with `notes` and
+/// followed by the result of checking its ASCII lookalike, if any.
+fn print_check(crate_name: &str, check: Check, mut notes: Vec<String>, indent: &str) {
+    let emoji = match check.availability {
+        Availability::Available => SUCCESS_SYMBOL,
+        Availability::Unavailable | Availability::Invalid(_) | Availability::Reserved => {
+            ERROR_SYMBOL
+        }
+        Availability::Unknown => UNKNOWN_SYMBOL,
+    };
+
+    match check.availability {
+        Availability::Invalid(reason) => notes.push(format!("invalid, {}", reason)),
+        Availability::Reserved => notes.push("reserved by crates.io".to_string()),
+        _ => {}
+    }
+    // Mention each confusable character once, in order of appearance.
+    let mut seen = HashSet::new();
+    for confusable in &check.confusables {
+        if !seen.insert(confusable.character) {
+            continue;
+        }
+        notes.push(format!(
+            "`{}` (U+{:04X}) resembles `{}`",
+            confusable.character, confusable.character as u32, confusable.ascii
+        ));
+    }
+    if let Some(taken_as) = check.taken_as.filter(|taken_as| taken_as != crate_name) {
+        notes.push(format!("taken as `{}`", taken_as));
+    }
+    if let Some(summary) = check.summary {
+        if let Some(created_at) = summary.created_at {
+            notes.push(format!("created {}", date(created_at)));
+        }
+        if let Some(downloads) = summary.downloads {
+            notes.push(format!("{} downloads", downloads));
+        }
+    }
+    if let Some(as_of) = check.as_of {
+        notes.push(format!("possibly stale, data from {}", date(as_of)));
+    }
+    if let Some(source) = check.source {
+        notes.push(format!("answered by `{}`", source));
+    }
+
+    if notes.is_empty() {
+        println!("{}{} {}", indent, emoji, crate_name);
+    } else {
+        println!("{}{} {}: {}", indent, emoji, crate_name, notes.join(", "));
+    }
+
+    if let Some(skeleton) = check.skeleton {
+        let notes = vec!["ASCII lookalike".to_string()];
+        match *skeleton {
+            Ok(skeleton) => print_check(&skeleton.name.clone(), skeleton, notes, "  "),
+            Err(e) => println!(
+                "  {} {}: {}, {}",
+                WARNING_SYMBOL,
+                ascii_skeleton(crate_name).unwrap_or_default(),
+                notes.join(", "),
+                e
+            ),
+        }
+    }
+}
+
+/// Warns that `crate_name` resembles the given popular crates.
+fn print_similar(crate_name: &str, similar: &[Similarity]) {
+    if similar.is_empty() {
+        return;
+    }
+
+    let matches = similar
+        .iter()
+        .map(|similarity| format!("`{}` ({})", similarity.name, similarity.kind))
+        .collect::<Vec<_>>();
+    println!(
+        "{} {}: resembles {}, crates.io may reject it as typosquatting",
+        WARNING_SYMBOL,
+        crate_name,
+        matches.join(", ")
+    );
+}
+
+/// Prints a table with one row per name and one column per registry,
+/// followed by the errors that occurred.
+fn print_matrix(registries: &[String], names: &[String], results: &[Vec<Result<Check, Error>>]) {
+    let name_width = names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let widths = registries
+        .iter()
+        .map(|registry| registry.chars().count())
+        .collect::<Vec<_>>();
+
+    print!("{:width$}", "", width = name_width);
+    for registry in registries {
+        print!("  {}", registry);
+    }
+    println!();
+    for (i, crate_name) in names.iter().enumerate() {
+        print!("{:width$}", crate_name, width = name_width);
+        for (checks, width) in results.iter().zip(&widths) {
+            let symbol = match &checks[i] {
+                Ok(check) => match check.availability {
+                    Availability::Available => SUCCESS_SYMBOL,
+                    Availability::Unavailable
+                    | Availability::Invalid(_)
+                    | Availability::Reserved => ERROR_SYMBOL,
+                    Availability::Unknown => UNKNOWN_SYMBOL,
+                },
+                Err(_) => WARNING_SYMBOL,
+            };
+            // The symbols may contain color codes and can't be padded by the
+            // formatter.
+            print!("  {}{:width$}", symbol, "", width = width - 1);
+        }
+        println!();
+    }
+
+    for (i, crate_name) in names.iter().enumerate() {
+        let lookalikes = registries
+            .iter()
+            .zip(results)
+            .filter_map(|(registry, checks)| {
+                let skeleton = checks[i].as_ref().ok()?.skeleton.as_ref()?;
+                Some((registry, skeleton))
+            })
+            .collect::<Vec<_>>();
+        if !lookalikes.is_empty() {
+            let availabilities = lookalikes
+                .iter()
+                .map(|(registry, skeleton)| match skeleton.as_ref() {
+                    Ok(skeleton) => format!("{} in {}", skeleton.availability, registry),
+                    Err(e) => format!("unknown in {} ({})", registry, e),
+                })
+                .collect::<Vec<_>>();
+            println!(
+                "{} {} resembles the ASCII name `{}`: {}",
+                INFO_SYMBOL,
+                crate_name,
+                ascii_skeleton(crate_name).unwrap_or_default(),
+                availabilities.join(", ")
+            );
+        }
+    }
+
+    for (registry, checks) in registries.iter().zip(results) {
+        let source = checks
+            .iter()
+            .find_map(|result| result.as_ref().ok()?.source.as_ref());
+        if let Some(source) = source {
+            println!("{} {} answered by `{}`", INFO_SYMBOL, registry, source);
+        }
+        for (crate_name, result) in names.iter().zip(checks) {
+            if let Err(e) = result {
+                println!("{} {} ({}): {}", WARNING_SYMBOL, crate_name, registry, e);
+            }
+        }
+    }
+}
+
+fn print_info(info: &CrateInfo, report: Option<&SquatReport>) {
+    println!("{} {}", ERROR_SYMBOL, info.name);
+    let field = |label: &str, value: Option<String>| {
+        if let Some(value) = value {
+            println!("  {:<12} {}", label, value);
+        }
+    };
+    field("Description", info.description.clone());
+    field(
+        "Owners",
+        info.owners.as_ref().map(|owners| owners.join(", ")),
+    );
+    field("Repository", info.repository.clone());
+    field("Created", info.created_at.map(date));
+    field("Updated", info.updated_at.map(date));
+    field("Version", info.max_version.clone());
+    field(
+        "Downloads",
+        info.downloads.map(|downloads| match info.recent_downloads {
+            Some(recent) => format!("{} ({} in the last 90 days)", downloads, recent),
+            None => downloads.to_string(),
+        }),
+    );
+    if info.yanked == Some(true) {
+        field("Yanked", Some("all versions".to_string()));
+    }
+    let dormancy = info.dormancy();
+    field(
+        "Activity",
+        activity(&dormancy).map(|activity| {
+            if dormancy.is_dormant() {
+                format!("dormant, {}", activity)
+            } else {
+                activity
+            }
+        }),
+    );
+    field(
+        "Squatting",
+        report.map(|report| {
+            let likelihood = if report.is_likely() {
+                "likely"
+            } else {
+                "unlikely"
             };
-            println!("{} {}", emoji, crate_name);
+            let signals = report
+                .signals
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            if signals.is_empty() {
+                format!("{}, score {}", likelihood, report.score)
+            } else {
+                format!(
+                    "{}, score {}: {}",
+                    likelihood,
+                    report.score,
+                    signals.join(", ")
+                )
+            }
+        }),
+    );
+}
+
+/// Describes the activity of a crate, e.g. `last release 2021-03-22,
+/// downloads falling`.
+fn activity(dormancy: &Dormancy) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(last_published) = dormancy.last_published {
+        parts.push(format!("last release {}", date(last_published)));
+    }
+    if let Some(trend) = dormancy.download_trend {
+        parts.push(format!("downloads {}", trend_to_str(trend)));
+    }
+    if dormancy.unmaintained {
+        parts.push("marked unmaintained".to_string());
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(", "))
+    }
+}
+
+fn trend_to_str(trend: DownloadTrend) -> &'static str {
+    match trend {
+        DownloadTrend::Rising => "rising",
+        DownloadTrend::Steady => "steady",
+        DownloadTrend::Falling => "falling",
+    }
+}
+
+fn info_to_json(crate_name: &str, info: &CrateInfo) -> Value {
+    let dormancy = info.dormancy();
+    json!({
+        "crate": crate_name,
+        "taken": true,
+        "name": info.name,
+        "owners": info.owners,
+        "description": info.description,
+        "repository": info.repository,
+        "created_at": info.created_at.map(unix_secs),
+        "updated_at": info.updated_at.map(unix_secs),
+        "max_version": info.max_version,
+        "downloads": info.downloads,
+        "recent_downloads": info.recent_downloads,
+        "yanked": info.yanked,
+        "published_at": info.published_at.map(unix_secs),
+        "maintenance": info.maintenance,
+        "dormancy": {
+            "dormant": dormancy.is_dormant(),
+            "last_published": dormancy.last_published.map(unix_secs),
+            "unmaintained": dormancy.unmaintained,
+            "download_trend": dormancy.download_trend.map(trend_to_str),
+        },
+    })
+}
+
+fn check_to_json(check: Check) -> Value {
+    let mut object = json!({
+        "crate": check.name,
+        "canonical": check.canonical,
+        "availability": check.availability.to_string(),
+    });
+    if let Availability::Invalid(reason) = check.availability {
+        object["reason"] = json!(reason.to_string());
+    }
+    if let Some(taken_as) = check.taken_as {
+        object["taken_as"] = json!(taken_as);
+    }
+    if let Some(summary) = check.summary {
+        if let Some(created_at) = summary.created_at {
+            object["created_at"] = json!(unix_secs(created_at));
+        }
+        if let Some(downloads) = summary.downloads {
+            object["downloads"] = json!(downloads);
+        }
+    }
+    if let Some(as_of) = check.as_of {
+        object["possibly_stale"] = json!(true);
+        object["as_of"] = json!(unix_secs(as_of));
+    }
+    if let Some(source) = check.source {
+        object["source"] = json!(source);
+    }
+    if !check.confusables.is_empty() {
+        object["confusables"] = check
+            .confusables
+            .iter()
+            .map(|confusable| {
+                json!({
+                    "position": confusable.position,
+                    "character": confusable.character.to_string(),
+                    "code_point": format!("U+{:04X}", confusable.character as u32),
+                    "ascii": confusable.ascii,
+                })
+            })
+            .collect();
+    }
+    if let Some(skeleton) = check.skeleton {
+        object["skeleton"] = match *skeleton {
+            Ok(skeleton) => check_to_json(skeleton),
+            Err(e) => json!({
+                "crate": ascii_skeleton(&check.name),
+                "error": error_to_json(&e),
+            }),
+        };
+    }
+
+    object
+}
+
+fn similar_to_json(similar: &[Similarity]) -> Value {
+    similar
+        .iter()
+        .map(|similarity| {
+            let mut object = json!({
+                "crate": similarity.name,
+                "downloads": similarity.downloads,
+                "message": similarity.kind.to_string(),
+            });
+            match &similarity.kind {
+                SimilarityKind::Separators => object["kind"] = json!("separators"),
+                SimilarityKind::Swap => object["kind"] = json!("swap"),
+                SimilarityKind::Edits(distance) => {
+                    object["kind"] = json!("edits");
+                    object["distance"] = json!(distance);
+                }
+                SimilarityKind::Affix(affix) => {
+                    object["kind"] = json!("affix");
+                    object["affix"] = json!(affix);
+                }
+                _ => object["kind"] = json!("other"),
+            }
+            object
+        })
+        .collect()
+}
+
+fn error_to_json(e: &Error) -> Value {
+    let kind = match e {
+        Error::EmptyCrateName => "empty_crate_name",
+        Error::InvalidCrateName(_) => "invalid_crate_name",
+        Error::ReservedCrateName => "reserved_crate_name",
+        Error::NetworkTimeout(_) => "timeout",
+        Error::Transport(_) => "transport",
+        Error::RateLimited { .. } => "rate_limited",
+        Error::ServerError(_) => "server_error",
+        Error::InvalidResponse(_) => "invalid_response",
+        Error::Io(_) => "io",
+        Error::UnknownRegistry(_) => "unknown_registry",
+        Error::Unauthorized(_) => "unauthorized",
+        Error::Credential(_) => "credential",
+        Error::SourceReplacement(_) => "source_replacement",
+        Error::Unresolved => "unresolved",
+    };
+    let mut object = json!({
+        "kind": kind,
+        "message": e.to_string(),
+        "transient": e.is_transient(),
+    });
+    match e {
+        Error::RateLimited {
+            retry_after: Some(retry_after),
+        } => object["retry_after"] = json!(retry_after.as_secs()),
+        Error::ServerError(status) | Error::Unauthorized(status) => {
+            object["status"] = json!(status)
+        }
+        _ => {}
+    }
+
+    object
+}
+
+/// Formats `time` as a date, e.g. `2021-03-22`.
+fn date(time: SystemTime) -> String {
+    let mut date = humantime::format_rfc3339_seconds(time).to_string();
+    date.truncate(10);
+    date
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(name: &str, availability: Availability) -> Result<Check, Error> {
+        Ok(Check {
+            name: name.to_string(),
+            canonical: name.to_string(),
+            availability,
+            taken_as: None,
+            as_of: None,
+            summary: None,
+            source: None,
+            confusables: Vec::new(),
+            skeleton: None,
+        })
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn owner_urls() {
+        assert_eq!(owner_url("dtolnay"), "https://crates.io/users/dtolnay");
+        assert_eq!(
+            owner_url("github:serde-rs:publish"),
+            "https://crates.io/teams/github:serde-rs:publish"
+        );
+    }
+
+    #[test]
+    fn matrix_exit_codes() {
+        let names = names(&["foo", "bar"]);
+        let free = vec![
+            check("foo", Availability::Available),
+            check("bar", Availability::Available),
+        ];
+        let taken = vec![
+            check("foo", Availability::Available),
+            check("bar", Availability::Unavailable),
+        ];
+        let failed = vec![
+            check("foo", Availability::Available),
+            Err(Error::ServerError(503)),
+        ];
+
+        assert_eq!(matrix_exit_code(&names, &[free.clone(), free.clone()]), 0);
+        assert_eq!(matrix_exit_code(&names, &[free.clone(), taken.clone()]), 2);
+        assert_eq!(matrix_exit_code(&names, &[free, failed.clone()]), 1);
+        // Errors take precedence over names that are not free.
+        assert_eq!(matrix_exit_code(&names, &[taken, failed]), 1);
+    }
+
+    #[test]
+    fn unknown_and_reserved_names_are_not_free() {
+        let names = names(&["foo"]);
+        for availability in [Availability::Unknown, Availability::Reserved] {
+            let results = [
+                vec![check("foo", Availability::Available)],
+                vec![check("foo", availability)],
+            ];
+            assert_eq!(matrix_exit_code(&names, &results), 2);
         }
     }
+
+    #[test]
+    fn matrix_json() {
+        let registries = names(&["crates-io", "my-registry"]);
+        let results = [
+            vec![
+                check("foo", Availability::Available),
+                check("sedre", Availability::Available),
+            ],
+            vec![
+                check("foo", Availability::Available),
+                Err(Error::ServerError(503)),
+            ],
+        ];
+        let popular = PopularCrates::new(vec![("serde", 100)], 10);
+        let json = matrix_to_json(&registries, &names(&["foo", "sedre"]), &results, |name| {
+            popular.similar_to(name)
+        });
+
+        assert_eq!(
+            json[0],
+            json!({
+                "crate": "foo",
+                "free_everywhere": true,
+                "registries": {
+                    "crates-io": {
+                        "crate": "foo",
+                        "canonical": "foo",
+                        "availability": "available",
+                    },
+                    "my-registry": {
+                        "crate": "foo",
+                        "canonical": "foo",
+                        "availability": "available",
+                    },
+                },
+            })
+        );
+        assert_eq!(json[1]["free_everywhere"], false);
+        assert_eq!(
+            json[1]["registries"]["crates-io"]["availability"],
+            "available"
+        );
+        assert_eq!(
+            json[1]["registries"]["my-registry"],
+            json!({
+                "crate": "sedre",
+                "error": error_to_json(&Error::ServerError(503)),
+            })
+        );
+        assert_eq!(json[1]["similar"][0]["crate"], "serde");
+        assert_eq!(json[1]["similar"][0]["kind"], "swap");
+    }
 }
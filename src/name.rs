@@ -0,0 +1,143 @@
+use std::{collections::HashSet, sync::OnceLock};
+use thiserror::Error;
+
+/// The maximum length of a crate name accepted by crates.io.
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// The bundled list of reserved crate names, one name per line.
+const RESERVED_NAMES: &str = include_str!("reserved.txt");
+
+/// The reason why crates.io would reject a crate name.
+#[derive(Clone, Copy, Debug, Eq, Error, Hash, PartialEq)]
+pub enum InvalidReason {
+    #[error("name is empty")]
+    Empty,
+
+    #[error("name is {0} characters long, the maximum is {}", MAX_NAME_LENGTH)]
+    TooLong(usize),
+
+    #[error("name must start with an ASCII letter, found `{0}`")]
+    InvalidStart(char),
+
+    #[error("invalid character `{0}`, only ASCII letters, digits, `-` and `_` are allowed")]
+    InvalidCharacter(char),
+}
+
+/// Validates a crate name against the rules enforced by crates.io, without
+/// touching the network.
+///
+/// A valid name is non-empty, at most [`MAX_NAME_LENGTH`] characters long,
+/// starts with an ASCII letter and consists solely of ASCII alphanumerics,
+/// `-` and `_`.
+///
+/// # Returns
+///
+/// `Ok(())` if the name is valid, `Err(InvalidReason)` describing the first
+/// violated rule otherwise.
+pub fn validate_name(name: impl AsRef<str>) -> Result<(), InvalidReason> {
+    let name = name.as_ref();
+    let first = name.chars().next().ok_or(InvalidReason::Empty)?;
+
+    let length = name.chars().count();
+    if length > MAX_NAME_LENGTH {
+        return Err(InvalidReason::TooLong(length));
+    }
+
+    if !first.is_ascii_alphabetic() {
+        return Err(InvalidReason::InvalidStart(first));
+    }
+
+    match name
+        .chars()
+        .find(|&c| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+    {
+        Some(c) => Err(InvalidReason::InvalidCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+/// Returns `true` if crates.io reserves `name`, e.g. `std` or `nul`.
+///
+/// Reserved names are rejected on publish even though no crate with that
+/// name exists. The check is performed against the list bundled with this
+/// crate, [`CheckerBuilder::reserved_names`](crate::CheckerBuilder::reserved_names)
+/// can be used to extend it.
+pub fn is_reserved(name: impl AsRef<str>) -> bool {
+    static RESERVED: OnceLock<HashSet<String>> = OnceLock::new();
+    RESERVED
+        .get_or_init(|| {
+            RESERVED_NAMES
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(canonical_name)
+                .collect()
+        })
+        .contains(&canonical_name(name.as_ref()))
+}
+
+/// Returns the canonical form crates.io uses to compare crate names:
+/// lowercase, with `-` replaced by `_`.
+///
+/// Two names with the same canonical form can't coexist on crates.io, e.g.
+/// `foo-bar`, `foo_bar` and `Foo_Bar` all refer to the same crate.
+pub fn canonical_name(name: impl AsRef<str>) -> String {
+    name.as_ref().to_ascii_lowercase().replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_names() {
+        assert_eq!(validate_name("serde"), Ok(()));
+        assert_eq!(validate_name("Foo-Bar_2"), Ok(()));
+        assert_eq!(validate_name("a".repeat(MAX_NAME_LENGTH)), Ok(()));
+    }
+
+    #[test]
+    fn invalid_names() {
+        assert_eq!(validate_name(""), Err(InvalidReason::Empty));
+        assert_eq!(
+            validate_name("a".repeat(MAX_NAME_LENGTH + 1)),
+            Err(InvalidReason::TooLong(MAX_NAME_LENGTH + 1))
+        );
+        assert_eq!(validate_name("-foo"), Err(InvalidReason::InvalidStart('-')));
+        assert_eq!(validate_name("_foo"), Err(InvalidReason::InvalidStart('_')));
+        assert_eq!(validate_name("1foo"), Err(InvalidReason::InvalidStart('1')));
+        assert_eq!(
+            validate_name("foo.bar"),
+            Err(InvalidReason::InvalidCharacter('.'))
+        );
+        assert_eq!(
+            validate_name("foo bar"),
+            Err(InvalidReason::InvalidCharacter(' '))
+        );
+        assert_eq!(
+            validate_name("s\u{0435}rde"),
+            Err(InvalidReason::InvalidCharacter('\u{0435}'))
+        );
+    }
+
+    #[test]
+    fn length_counts_characters() {
+        let name = format!("a{}", "\u{0435}".repeat(MAX_NAME_LENGTH - 1));
+        assert_eq!(
+            validate_name(name),
+            Err(InvalidReason::InvalidCharacter('\u{0435}'))
+        );
+    }
+
+    #[test]
+    fn reserved_names() {
+        assert!(is_reserved("std"));
+        assert!(is_reserved("NUL"));
+        assert!(!is_reserved("serde"));
+    }
+
+    #[test]
+    fn canonical_names() {
+        assert_eq!(canonical_name("Foo-Bar_baz"), "foo_bar_baz");
+    }
+}
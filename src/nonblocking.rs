@@ -0,0 +1,184 @@
+//! An asynchronous API, available with the `async` feature.
+//!
+//! Checks are run on a thread pool dedicated to blocking work, so they never
+//! block the executor. The API works with any async runtime.
+//!
+//! This is by design: the checks reuse the blocking HTTP client, retries and
+//! rate limit of the synchronous API, while an async HTTP client would tie
+//! the crate to a specific runtime. As the rate limit keeps the number of
+//! concurrent requests low, the blocking threads cost little. Each pending
+//! call occupies a thread of the pool though, so pass many names to
+//! [`Checker::check_many`] instead of spawning one check per name.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), cargo_free::Error> {
+//! use cargo_free::{nonblocking, Availability};
+//!
+//! let availability = nonblocking::check_availability("serde").await?;
+//! assert_eq!(availability, Availability::Unavailable);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{default_checker, Availability, Check, CrateInfo, Error};
+use std::{sync::Arc, time::Duration};
+
+/// Checks the availability for a given crate name.
+///
+/// The asynchronous version of [`check_availability`](crate::check_availability).
+pub async fn check_availability(name: impl AsRef<str>) -> Result<Availability, Error> {
+    let name = name.as_ref().to_string();
+    blocking::unblock(move || default_checker().check_availability(name)).await
+}
+
+/// Checks the availability for a given crate name. Stops after the given
+/// timeout duration and returns `Error::NetworkTimeout`.
+///
+/// The asynchronous version of
+/// [`check_availability_with_timeout`](crate::check_availability_with_timeout).
+pub async fn check_availability_with_timeout(
+    name: impl AsRef<str>,
+    timeout: Duration,
+) -> Result<Availability, Error> {
+    let name = name.as_ref().to_string();
+    blocking::unblock(move || crate::check_availability_with_timeout(name, timeout)).await
+}
+
+/// Looks up the metadata of the crate using a given name.
+///
+/// The asynchronous version of [`lookup`](crate::lookup).
+pub async fn lookup(name: impl AsRef<str>) -> Result<Option<CrateInfo>, Error> {
+    let name = name.as_ref().to_string();
+    blocking::unblock(move || default_checker().lookup(name)).await
+}
+
+/// An asynchronous wrapper around a configured [`Checker`](crate::Checker).
+///
+/// Cloning is cheap, all clones share the same underlying checker.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), cargo_free::Error> {
+/// use cargo_free::{nonblocking, Checker};
+///
+/// let checker: nonblocking::Checker = Checker::builder().build().into();
+/// let check = checker.check("serde").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Checker {
+    inner: Arc<crate::Checker>,
+}
+
+impl Checker {
+    /// Creates a checker using the default configuration.
+    pub fn new() -> Self {
+        crate::Checker::new().into()
+    }
+
+    /// Checks the availability for a given crate name, see
+    /// [`Checker::check_availability`](crate::Checker::check_availability).
+    pub async fn check_availability(&self, name: impl AsRef<str>) -> Result<Availability, Error> {
+        let inner = Arc::clone(&self.inner);
+        let name = name.as_ref().to_string();
+        blocking::unblock(move || inner.check_availability(name)).await
+    }
+
+    /// Checks a given crate name, see [`Checker::check`](crate::Checker::check).
+    pub async fn check(&self, name: impl AsRef<str>) -> Result<Check, Error> {
+        let inner = Arc::clone(&self.inner);
+        let name = name.as_ref().to_string();
+        blocking::unblock(move || inner.check(name)).await
+    }
+
+    /// Looks up the metadata of the crate using a given name, see
+    /// [`Checker::lookup`](crate::Checker::lookup).
+    pub async fn lookup(&self, name: impl AsRef<str>) -> Result<Option<CrateInfo>, Error> {
+        let inner = Arc::clone(&self.inner);
+        let name = name.as_ref().to_string();
+        blocking::unblock(move || inner.lookup(name)).await
+    }
+
+    /// Checks many crate names at once, see
+    /// [`Checker::check_many`](crate::Checker::check_many).
+    pub async fn check_many<I, S>(&self, names: I) -> Vec<Result<Check, Error>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let inner = Arc::clone(&self.inner);
+        let names = names
+            .into_iter()
+            .map(|name| name.as_ref().to_string())
+            .collect::<Vec<_>>();
+        blocking::unblock(move || inner.check_many(names)).await
+    }
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<crate::Checker> for Checker {
+    fn from(checker: crate::Checker) -> Self {
+        Self {
+            inner: Arc::new(checker),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Response, Server};
+    use futures_lite::future;
+
+    #[test]
+    fn check() {
+        let server = Server::new(vec![(
+            "/api/v1/crates/serde",
+            Response::ok(r#"{"crate":{"name":"serde"}}"#),
+        )]);
+        let checker: Checker = crate::Checker::builder()
+            .base_url(server.url())
+            .build()
+            .into();
+
+        let (serde, free) = future::block_on(future::zip(
+            checker.check("serde"),
+            checker.check_availability("free"),
+        ));
+        assert_eq!(serde.unwrap().taken_as.as_deref(), Some("serde"));
+        assert_eq!(free, Ok(Availability::Available));
+        assert_eq!(server.paths().len(), 2);
+    }
+
+    #[test]
+    fn check_many() {
+        let server = Server::new(vec![(
+            "/api/v1/crates/serde",
+            Response::ok(r#"{"crate":{"name":"serde"}}"#),
+        )]);
+        let checker: Checker = crate::Checker::builder()
+            .base_url(server.url())
+            .build()
+            .into();
+
+        let results = future::block_on(checker.check_many(["serde", "Serde", "free"]));
+        let availabilities = results
+            .into_iter()
+            .map(|result| result.unwrap().availability)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            availabilities,
+            [
+                Availability::Unavailable,
+                Availability::Unavailable,
+                Availability::Available
+            ]
+        );
+        assert_eq!(server.paths().len(), 2);
+    }
+}
@@ -0,0 +1,486 @@
+//! Warnings for names resembling popular crates.
+//!
+//! A name one typo away from a widely used crate, e.g. `serdo` or `tokio-rs`,
+//! looks like an attempt at typosquatting and may be rejected or removed by
+//! the crates.io moderators.
+
+use crate::{dump::DumpStore, name::canonical_name, Error};
+use std::{cmp::Reverse, collections::HashMap, fmt, path::Path};
+
+/// The maximum number of similar crates reported per name.
+const MAX_MATCHES: usize = 3;
+
+/// The maximum edit distance to long names of popular crates.
+const MAX_DISTANCE: usize = 2;
+
+/// Popular crates with shorter names are not checked for affixes, e.g.
+/// `rs` + `ab`.
+const MIN_AFFIXED: usize = 3;
+
+/// Words commonly put in front of a crate's name to create a variant of it.
+const PREFIXES: &[&str] = &["rust", "rs", "lib"];
+
+/// Words commonly appended to a crate's name to create a variant of it.
+const SUFFIXES: &[&str] = &["rs", "rust", "lib", "ng", "2", "3"];
+
+/// How a name resembles a popular crate, see [`PopularCrates::similar_to`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum SimilarityKind {
+    /// The names only differ in their `-` and `_` separators, e.g.
+    /// `serdejson` and `serde_json`.
+    Separators,
+
+    /// Two adjacent characters are swapped, e.g. `sedre` and `serde`.
+    Swap,
+
+    /// The names are within a small edit distance, e.g. `serdo` and `serde`.
+    /// Contains the Damerau-Levenshtein distance.
+    Edits(usize),
+
+    /// The name adds a common prefix or suffix, e.g. `serde-rs` or
+    /// `rust-serde`. Contains the added word.
+    Affix(String),
+}
+
+impl SimilarityKind {
+    /// Orders the kinds from the most to the least suspicious.
+    fn rank(&self) -> usize {
+        match self {
+            SimilarityKind::Separators => 0,
+            SimilarityKind::Swap => 1,
+            SimilarityKind::Edits(distance) => 2 * distance,
+            SimilarityKind::Affix(_) => 3,
+        }
+    }
+}
+
+impl fmt::Display for SimilarityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimilarityKind::Separators => write!(f, "differs only in separators"),
+            SimilarityKind::Swap => write!(f, "two characters swapped"),
+            SimilarityKind::Edits(1) => write!(f, "one edit away"),
+            SimilarityKind::Edits(distance) => write!(f, "{} edits away", distance),
+            SimilarityKind::Affix(affix) => write!(f, "adds `{}`", affix),
+        }
+    }
+}
+
+/// A popular crate a name resembles.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Similarity {
+    /// The name of the popular crate.
+    pub name: String,
+
+    /// The total downloads of the popular crate.
+    pub downloads: u64,
+
+    /// How the names resemble each other.
+    pub kind: SimilarityKind,
+}
+
+#[derive(Clone, Debug)]
+struct PopularCrate {
+    name: String,
+    canonical: String,
+    /// The canonical name without separators.
+    bare: String,
+    downloads: u64,
+}
+
+/// A name prepared for comparison with many popular crates.
+struct Candidate {
+    canonical: String,
+    bare: String,
+    /// How often each ASCII character occurs in the canonical name.
+    histogram: [u8; 128],
+}
+
+impl Candidate {
+    fn new(canonical: String) -> Self {
+        let mut histogram = [0u8; 128];
+        for b in canonical.bytes() {
+            histogram[usize::from(b)] = histogram[usize::from(b)].saturating_add(1);
+        }
+        Self {
+            bare: canonical.replace('_', ""),
+            canonical,
+            histogram,
+        }
+    }
+
+    /// Returns a lower bound of the edit distance to `other`, based on the
+    /// characters the names don't have in common.
+    fn bag_distance(&self, other: &str) -> usize {
+        let mut histogram = self.histogram;
+        let mut common = 0;
+        for b in other.bytes() {
+            let count = &mut histogram[usize::from(b)];
+            if *count > 0 {
+                *count -= 1;
+                common += 1;
+            }
+        }
+        (self.canonical.len() - common).max(other.len() - common)
+    }
+}
+
+/// The most downloaded crates, used to warn about names resembling them.
+///
+/// The crates are indexed by their name without separators and by length, so
+/// checking a name only compares it to the few crates it may resemble.
+#[derive(Clone, Debug, Default)]
+pub struct PopularCrates {
+    crates: Vec<PopularCrate>,
+    by_bare: HashMap<String, Vec<usize>>,
+    by_length: Vec<Vec<usize>>,
+}
+
+impl PopularCrates {
+    /// Keeps the `top` most downloaded of the given crates.
+    ///
+    /// # Arguments
+    ///
+    /// - `crates`: The names of crates and their total downloads
+    /// - `top`: The number of crates to keep
+    pub fn new<I, S>(crates: I, top: usize) -> Self
+    where
+        I: IntoIterator<Item = (S, u64)>,
+        S: AsRef<str>,
+    {
+        // Crates.io only allows ASCII names, other names couldn't be confused
+        // with a valid one by a typo.
+        let mut crates = crates
+            .into_iter()
+            .filter(|(name, _)| name.as_ref().is_ascii())
+            .map(|(name, downloads)| {
+                let name = name.as_ref();
+                let canonical = canonical_name(name);
+                PopularCrate {
+                    name: name.to_string(),
+                    bare: canonical.replace('_', ""),
+                    canonical,
+                    downloads,
+                }
+            })
+            .collect::<Vec<_>>();
+        crates.sort_by_key(|krate| Reverse(krate.downloads));
+        crates.truncate(top);
+
+        let mut by_bare = HashMap::<_, Vec<_>>::new();
+        let mut by_length = Vec::<Vec<_>>::new();
+        for (i, krate) in crates.iter().enumerate() {
+            by_bare.entry(krate.bare.clone()).or_default().push(i);
+            let length = krate.canonical.len();
+            if by_length.len() <= length {
+                by_length.resize_with(length + 1, Vec::new);
+            }
+            by_length[length].push(i);
+        }
+
+        Self {
+            crates,
+            by_bare,
+            by_length,
+        }
+    }
+
+    /// Keeps the `top` most downloaded crates of a store imported via
+    /// [`import_db_dump`](crate::import_db_dump).
+    pub fn from_db_dump(path: impl AsRef<Path>, top: usize) -> Result<Self, Error> {
+        let store = DumpStore::load(path.as_ref())?;
+        Ok(Self::new(
+            store
+                .crates
+                .into_values()
+                .filter_map(|krate| Some((krate.name, krate.downloads?))),
+            top,
+        ))
+    }
+
+    /// Returns the number of popular crates.
+    pub fn len(&self) -> usize {
+        self.crates.len()
+    }
+
+    /// Returns `true` if there are no popular crates.
+    pub fn is_empty(&self) -> bool {
+        self.crates.is_empty()
+    }
+
+    /// Returns the popular crates `name` resembles, the closest ones first.
+    /// At most three crates are returned, none if `name` is a popular crate
+    /// itself.
+    ///
+    /// ```
+    /// use cargo_free::{PopularCrates, SimilarityKind};
+    ///
+    /// let popular = PopularCrates::new(vec![("serde", 300), ("tokio", 200)], 1_000);
+    /// let similar = popular.similar_to("sedre");
+    /// assert_eq!(similar[0].name, "serde");
+    /// assert_eq!(similar[0].kind, SimilarityKind::Swap);
+    /// assert!(popular.similar_to("tokio").is_empty());
+    /// ```
+    pub fn similar_to(&self, name: &str) -> Vec<Similarity> {
+        let canonical = canonical_name(name);
+        // Names with non-ASCII characters are invalid anyway, see
+        // `confusables` for the ones resembling ASCII names.
+        if !canonical.is_ascii() {
+            return Vec::new();
+        }
+        let candidate = Candidate::new(canonical);
+        let bare = |bare: &str| self.by_bare.get(bare).into_iter().flatten().copied();
+        if bare(&candidate.bare).any(|i| self.crates[i].canonical == candidate.canonical) {
+            return Vec::new();
+        }
+
+        // A crate may resemble the name in several ways, the most suspicious
+        // one found first is kept.
+        let mut found = Vec::<(usize, SimilarityKind)>::new();
+        let mut add = |i: usize, kind: SimilarityKind| {
+            if !found.iter().any(|(j, _)| *j == i) {
+                found.push((i, kind));
+            }
+        };
+
+        for i in bare(&candidate.bare) {
+            add(i, SimilarityKind::Separators);
+        }
+
+        // Swaps and edits don't change the length by more than the maximum
+        // distance.
+        let length = candidate.canonical.len();
+        let lengths = length.saturating_sub(MAX_DISTANCE)..=length + MAX_DISTANCE;
+        for i in lengths
+            .filter_map(|length| self.by_length.get(length))
+            .flatten()
+            .copied()
+        {
+            if let Some(kind) = edits(&candidate, &self.crates[i]) {
+                add(i, kind);
+            }
+        }
+
+        for prefix in PREFIXES {
+            if let Some(rest) = candidate.bare.strip_prefix(prefix) {
+                for i in bare(rest).filter(|&i| self.crates[i].bare.len() >= MIN_AFFIXED) {
+                    add(i, SimilarityKind::Affix(prefix.to_string()));
+                }
+            }
+        }
+        for suffix in SUFFIXES {
+            if let Some(rest) = candidate.bare.strip_suffix(suffix) {
+                for i in bare(rest).filter(|&i| self.crates[i].bare.len() >= MIN_AFFIXED) {
+                    add(i, SimilarityKind::Affix(suffix.to_string()));
+                }
+            }
+        }
+
+        let mut similar = found
+            .into_iter()
+            .map(|(i, kind)| Similarity {
+                name: self.crates[i].name.clone(),
+                downloads: self.crates[i].downloads,
+                kind,
+            })
+            .collect::<Vec<_>>();
+        similar.sort_by_key(|similarity| (similarity.kind.rank(), Reverse(similarity.downloads)));
+        similar.truncate(MAX_MATCHES);
+        similar
+    }
+}
+
+/// Returns whether `name` turns into the name of the `popular` crate by
+/// swapping two characters or a few edits.
+fn edits(name: &Candidate, popular: &PopularCrate) -> Option<SimilarityKind> {
+    let (a, b) = (&name.canonical, &popular.canonical);
+    if a.len() == b.len() && is_swap(a.as_bytes(), b.as_bytes()) {
+        return Some(SimilarityKind::Swap);
+    }
+
+    // Short names are a few edits away from lots of unrelated names.
+    let max_distance = match popular.bare.len() {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => MAX_DISTANCE,
+    };
+    // The distance is at least the length difference and the number of
+    // characters the names don't have in common, both cheaper to compute.
+    if max_distance == 0
+        || a.len().abs_diff(b.len()) > max_distance
+        || name.bag_distance(b) > max_distance
+    {
+        return None;
+    }
+
+    let distance = strsim::damerau_levenshtein(a, b);
+    if distance <= max_distance {
+        Some(SimilarityKind::Edits(distance))
+    } else {
+        None
+    }
+}
+
+/// Returns `true` if `a` turns into `b` by swapping two adjacent characters.
+fn is_swap(a: &[u8], b: &[u8]) -> bool {
+    let i = match (0..a.len()).find(|&i| a[i] != b[i]) {
+        Some(i) => i,
+        None => return false,
+    };
+    i + 1 < a.len() && a[i] == b[i + 1] && a[i + 1] == b[i] && a[i + 2..] == b[i + 2..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn popular() -> PopularCrates {
+        PopularCrates::new(
+            vec![
+                ("serde", 300),
+                ("serde_json", 250),
+                ("tokio", 200),
+                ("syn", 150),
+                ("rand", 100),
+                ("regex", 90),
+                ("obscure", 1),
+            ],
+            6,
+        )
+    }
+
+    fn kinds(name: &str) -> Vec<(String, SimilarityKind)> {
+        popular()
+            .similar_to(name)
+            .into_iter()
+            .map(|similarity| (similarity.name, similarity.kind))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_most_downloaded() {
+        let popular = popular();
+        assert_eq!(popular.len(), 6);
+        assert!(popular.similar_to("obscur").is_empty());
+    }
+
+    #[test]
+    fn popular_crates_are_not_similar() {
+        assert!(kinds("serde").is_empty());
+        assert!(kinds("Serde-JSON").is_empty());
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(
+            kinds("serdejson"),
+            [("serde_json".to_string(), SimilarityKind::Separators)]
+        );
+    }
+
+    #[test]
+    fn swap() {
+        assert_eq!(
+            kinds("sedre")[0],
+            ("serde".to_string(), SimilarityKind::Swap)
+        );
+        assert_eq!(kinds("sny")[0], ("syn".to_string(), SimilarityKind::Swap));
+    }
+
+    #[test]
+    fn edits() {
+        assert_eq!(
+            kinds("serdo")[0],
+            ("serde".to_string(), SimilarityKind::Edits(1))
+        );
+        assert_eq!(
+            kinds("serde_jsno")[0],
+            ("serde_json".to_string(), SimilarityKind::Swap)
+        );
+        assert_eq!(
+            kinds("serde_jzon")[0],
+            ("serde_json".to_string(), SimilarityKind::Edits(1))
+        );
+        assert_eq!(
+            kinds("sarde_jzon")[0],
+            ("serde_json".to_string(), SimilarityKind::Edits(2))
+        );
+        // Short names are not checked for edits.
+        assert!(kinds("rant").is_empty());
+    }
+
+    #[test]
+    fn affixes() {
+        assert_eq!(
+            kinds("tokio-rs"),
+            [("tokio".to_string(), SimilarityKind::Affix("rs".to_string()))]
+        );
+        assert_eq!(
+            kinds("rust-serde")[0],
+            (
+                "serde".to_string(),
+                SimilarityKind::Affix("rust".to_string())
+            )
+        );
+        // Popular crates with short names are not checked for affixes.
+        let popular = PopularCrates::new(vec![("cc", 100)], 1);
+        assert!(popular.similar_to("cc-rs").is_empty());
+    }
+
+    #[test]
+    fn closest_first() {
+        let popular = PopularCrates::new(
+            vec![
+                ("abcdeg", 10),
+                ("abcdeh", 20),
+                ("abcdei", 30),
+                ("abcdfe", 5),
+                ("abcdej", 40),
+            ],
+            5,
+        );
+        let names = popular
+            .similar_to("abcdef")
+            .into_iter()
+            .map(|similarity| similarity.name)
+            .collect::<Vec<_>>();
+        // Swaps rank before edits, which are ordered by downloads.
+        assert_eq!(names, ["abcdfe", "abcdej", "abcdei"]);
+    }
+
+    #[test]
+    fn non_ascii_names_are_skipped() {
+        assert!(kinds("s\u{0435}rde").is_empty());
+    }
+
+    #[test]
+    fn non_ascii_popular_crates_are_skipped() {
+        let popular = PopularCrates::new(vec![("s\u{e9}rdexx", 10), ("serdexy", 5)], 10);
+        assert_eq!(popular.len(), 1);
+        let similar = popular.similar_to("serdexx");
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].name, "serdexy");
+    }
+
+    #[test]
+    fn bag_distance_is_a_lower_bound() {
+        for (a, b) in [
+            ("serde", "sedre"),
+            ("serde", "serde_json"),
+            ("tokio", "regex"),
+        ] {
+            let candidate = Candidate::new(a.to_string());
+            assert!(candidate.bag_distance(b) <= strsim::damerau_levenshtein(a, b));
+        }
+    }
+
+    #[test]
+    fn swaps() {
+        assert!(is_swap(b"ab", b"ba"));
+        assert!(is_swap(b"serde", b"sedre"));
+        assert!(!is_swap(b"serde", b"serde"));
+        assert!(!is_swap(b"abc", b"cba"));
+    }
+}
@@ -0,0 +1,326 @@
+//! Compact snapshots of all crate names, stored as a finite-state transducer.
+//!
+//! A snapshot maps the canonical form of each crate name to a bit mask of the
+//! positions at which the crate's name uses `-` instead of `_`. This allows
+//! restoring the crate's spelling (apart from its case) while keeping the
+//! snapshot small enough to load entirely into memory.
+
+use crate::{dump::DumpStore, name::canonical_name, Error};
+use fst::{automaton::Levenshtein, Automaton, IntoStreamer, Map, MapBuilder, Streamer};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The magic bytes every snapshot file starts with. They are followed by
+/// the snapshot's timestamp (8 bytes, little endian) and the FST.
+const MAGIC: &[u8; 8] = b"cfsnap01";
+
+/// An in-memory snapshot of crate names supporting exact, prefix and fuzzy
+/// lookups.
+pub struct Snapshot {
+    map: Map<Vec<u8>>,
+    as_of: SystemTime,
+}
+
+impl Snapshot {
+    /// Builds a snapshot from crate names.
+    ///
+    /// # Arguments
+    ///
+    /// - `names`: The names of all crates
+    /// - `as_of`: The time the names were collected
+    pub fn from_names<I, S>(names: I, as_of: SystemTime) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        // The FST requires its keys to be inserted in lexicographic order.
+        let names = names
+            .into_iter()
+            .map(|name| {
+                let name = name.as_ref();
+                (canonical_name(name), dash_mask(name))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut builder = MapBuilder::memory();
+        builder.extend_iter(names).map_err(fst_error)?;
+        let map = builder.into_map();
+        Ok(Self { map, as_of })
+    }
+
+    /// Builds a snapshot from a store imported via
+    /// [`import_db_dump`](crate::import_db_dump).
+    pub fn from_db_dump(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let store = DumpStore::load(path.as_ref())?;
+        Self::from_names(
+            store.crates.values().map(|krate| krate.name.as_str()),
+            store.as_of,
+        )
+    }
+
+    /// Builds a snapshot from a checkout of a registry index, e.g. a clone of
+    /// `https://github.com/rust-lang/crates.io-index`.
+    pub fn from_index_dir(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut names = Vec::new();
+        collect_index_names(path, &mut names)?;
+
+        let as_of = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+        Self::from_names(names, as_of)
+    }
+
+    /// Loads a snapshot previously written by [`Snapshot::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| Error::io(path, e))?;
+        if bytes.len() < 16 || &bytes[..8] != MAGIC {
+            return Err(Error::Io(format!("{}: not a snapshot", path.display())));
+        }
+
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&bytes[8..16]);
+        let as_of = UNIX_EPOCH + Duration::from_secs(u64::from_le_bytes(timestamp));
+        let map = Map::new(bytes[16..].to_vec()).map_err(fst_error)?;
+        Ok(Self { map, as_of })
+    }
+
+    /// Writes the snapshot to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+
+        let timestamp = self
+            .as_of
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let fst = self.map.as_fst().as_bytes();
+        let mut bytes = Vec::with_capacity(16 + fst.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&timestamp.to_le_bytes());
+        bytes.extend_from_slice(fst);
+        fs::write(path, bytes).map_err(|e| Error::io(path, e))
+    }
+
+    /// Returns the time the names of the snapshot were collected.
+    pub fn as_of(&self) -> SystemTime {
+        self.as_of
+    }
+
+    /// Returns the number of crate names in the snapshot.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the snapshot contains no names.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the spelling of the crate occupying `name`, if any. The case of
+    /// the spelling is not preserved by snapshots.
+    pub fn get(&self, name: impl AsRef<str>) -> Option<String> {
+        let canonical = canonical_name(name);
+        self.map
+            .get(&canonical)
+            .map(|mask| spelling(&canonical, mask))
+    }
+
+    /// Returns the spellings of all crates whose canonical name starts with
+    /// the canonical form of `prefix`, in lexicographic order.
+    pub fn prefix(&self, prefix: impl AsRef<str>) -> Vec<String> {
+        let prefix = canonical_name(prefix);
+        self.search(fst::automaton::Str::new(&prefix).starts_with())
+    }
+
+    /// Returns the spellings of all crates whose canonical name is within the
+    /// given Levenshtein distance of the canonical form of `name`.
+    ///
+    /// Returns `Err(Error::Io)` if `distance` is too large to build the
+    /// search automaton.
+    pub fn fuzzy(&self, name: impl AsRef<str>, distance: u32) -> Result<Vec<String>, Error> {
+        let automaton = Levenshtein::new(&canonical_name(name), distance)
+            .map_err(|e| Error::Io(format!("invalid fuzzy query: {}", e)))?;
+        Ok(self.search(automaton))
+    }
+
+    /// Returns the spellings of all crates whose canonical name starts with a
+    /// string within the given Levenshtein distance of the canonical form of
+    /// `prefix`.
+    pub fn fuzzy_prefix(
+        &self,
+        prefix: impl AsRef<str>,
+        distance: u32,
+    ) -> Result<Vec<String>, Error> {
+        let automaton = Levenshtein::new(&canonical_name(prefix), distance)
+            .map_err(|e| Error::Io(format!("invalid fuzzy query: {}", e)))?;
+        Ok(self.search(automaton.starts_with()))
+    }
+
+    fn search(&self, automaton: impl Automaton) -> Vec<String> {
+        let mut stream = self.map.search(automaton).into_stream();
+        let mut names = Vec::new();
+        while let Some((key, mask)) = stream.next() {
+            // Keys are always canonical crate names and thus ASCII.
+            names.push(spelling(&String::from_utf8_lossy(key), mask));
+        }
+        names
+    }
+}
+
+/// Returns the default location of the snapshot, inside the user's data
+/// directory (e.g. `~/.local/share/cargo-free/names.fst` on Linux).
+pub fn default_snapshot_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("cargo-free").join("names.fst"))
+}
+
+/// Returns a mask with bit `i` set if the `i`-th character of `name` is `-`.
+/// Crate names are at most 64 characters long, so the mask fits into 64
+/// bits.
+fn dash_mask(name: &str) -> u64 {
+    name.chars()
+        .take(64)
+        .enumerate()
+        .filter(|&(_, c)| c == '-')
+        .fold(0, |mask, (i, _)| mask | 1 << i)
+}
+
+/// Restores the spelling of a crate from its canonical name and dash mask.
+fn spelling(canonical: &str, mask: u64) -> String {
+    canonical
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if i < 64 && mask & 1 << i != 0 { '-' } else { c })
+        .collect()
+}
+
+/// Collects the names of all index files below `dir`.
+fn collect_index_names(dir: &Path, names: &mut Vec<String>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir).map_err(|e| Error::io(dir, e))? {
+        let entry = entry.map_err(|e| Error::io(dir, e))?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        // Skip `.git`, `.cache` and the registry's `config.json`.
+        if name.starts_with('.') || name == "config.json" {
+            continue;
+        }
+
+        let path = entry.path();
+        if path.is_dir() {
+            collect_index_names(&path, names)?;
+        } else {
+            names.push(name.into_owned());
+        }
+    }
+    Ok(())
+}
+
+fn fst_error(e: fst::Error) -> Error {
+    Error::Io(format!("invalid snapshot: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    fn snapshot() -> Snapshot {
+        let names = [
+            "serde",
+            "serde_json",
+            "serde-xml-rs",
+            "Tokio",
+            "tokio-util",
+            "syn",
+        ];
+        Snapshot::from_names(names, UNIX_EPOCH + Duration::from_secs(1_600_000_000)).unwrap()
+    }
+
+    #[test]
+    fn dash_masks() {
+        assert_eq!(dash_mask("serde"), 0);
+        assert_eq!(dash_mask("a-b_c-d"), 0b10_0010);
+        assert_eq!(spelling("a_b_c_d", 0b10_0010), "a-b_c-d");
+        let name = format!("{}-", "a".repeat(63));
+        assert_eq!(spelling(&canonical_name(&name), dash_mask(&name)), name);
+    }
+
+    #[test]
+    fn get() {
+        let snapshot = snapshot();
+        assert_eq!(snapshot.len(), 6);
+        assert_eq!(snapshot.get("Serde-Json").as_deref(), Some("serde_json"));
+        assert_eq!(
+            snapshot.get("serde_xml_rs").as_deref(),
+            Some("serde-xml-rs")
+        );
+        // The case of the crate's name is lost.
+        assert_eq!(snapshot.get("tokio").as_deref(), Some("tokio"));
+        assert_eq!(snapshot.get("serde-yaml"), None);
+    }
+
+    #[test]
+    fn prefix() {
+        let snapshot = snapshot();
+        // Names are sorted by their canonical form.
+        assert_eq!(snapshot.prefix("Serde-"), ["serde_json", "serde-xml-rs"]);
+        assert_eq!(snapshot.prefix("tokio"), ["tokio", "tokio-util"]);
+        assert!(snapshot.prefix("rand").is_empty());
+    }
+
+    #[test]
+    fn fuzzy() {
+        let snapshot = snapshot();
+        assert_eq!(snapshot.fuzzy("serd", 1).unwrap(), ["serde"]);
+        assert_eq!(snapshot.fuzzy("sny", 2).unwrap(), ["syn"]);
+        assert!(snapshot.fuzzy("sny", 1).unwrap().is_empty());
+        assert!(matches!(snapshot.fuzzy("serde", 100), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn fuzzy_prefix() {
+        let snapshot = snapshot();
+        assert_eq!(
+            snapshot.fuzzy_prefix("tokoi", 2).unwrap(),
+            ["tokio", "tokio-util"]
+        );
+        assert!(snapshot.fuzzy_prefix("rand", 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn load_saved_snapshot() {
+        let path = temp_dir("snapshot").join("names.fst");
+        let snapshot = snapshot();
+        snapshot.save(&path).unwrap();
+
+        let loaded = Snapshot::load(&path).unwrap();
+        assert_eq!(loaded.as_of(), snapshot.as_of());
+        assert_eq!(loaded.prefix(""), snapshot.prefix(""));
+    }
+
+    #[test]
+    fn load_rejects_other_files() {
+        let path = temp_dir("snapshot-invalid").join("names.fst");
+        fs::write(&path, "not a snapshot at all").unwrap();
+        assert!(matches!(Snapshot::load(&path), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn from_index_dir() {
+        let dir = temp_dir("snapshot-index");
+        for path in ["3/f/foo", "fo/o-/foo-bar", ".git/config", "config.json"] {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "{}").unwrap();
+        }
+        let snapshot = Snapshot::from_index_dir(&dir).unwrap();
+        assert_eq!(snapshot.prefix(""), ["foo", "foo-bar"]);
+    }
+}
@@ -0,0 +1,507 @@
+//! Heuristics telling placeholder crates apart from crates in actual use.
+//!
+//! Names are often held by crates published once to reserve them: a `0.0.x`
+//! version, no repository, an empty library and few downloads. Such names
+//! may be worth requesting via the crates.io squatting policy.
+
+use crate::{CrateInfo, Error};
+use flate2::read::GzDecoder;
+use std::{
+    fmt,
+    io::Read,
+    time::{Duration, SystemTime},
+};
+
+/// The score from which a crate is considered a likely squat.
+const LIKELY_SCORE: u8 = 50;
+
+/// Crates with fewer downloads in total are considered unused.
+const FEW_DOWNLOADS: u64 = 1_000;
+
+/// Crates with fewer downloads in the last 90 days are considered unused.
+const FEW_RECENT_DOWNLOADS: u64 = 100;
+
+/// Words hinting at a crate published only to reserve the name.
+const PLACEHOLDER_WORDS: &[&str] = &[
+    "placeholder",
+    "reserved",
+    "reserving",
+    "squat",
+    "coming soon",
+    "work in progress",
+    "wip",
+];
+
+/// Crates that contain at most this many lines of code, ignoring blank lines,
+/// comments and the `cargo new` template, are considered empty.
+const EMPTY_CODE_LINES: usize = 5;
+
+/// A hint that a crate was published only to reserve its name.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum SquatSignal {
+    /// The highest version is a `0.0.x` placeholder. Contains the version.
+    PlaceholderVersion(String),
+
+    /// All published versions are yanked.
+    AllYanked,
+
+    /// The manifest names no repository.
+    NoRepository,
+
+    /// The manifest contains no description.
+    NoDescription,
+
+    /// The description says the name is reserved, e.g. "placeholder".
+    PlaceholderDescription,
+
+    /// The crate is hardly downloaded. Contains the total downloads.
+    FewDownloads(u64),
+
+    /// The crate was never updated after it was first published.
+    NeverUpdated,
+
+    /// The published package contains no code besides the `cargo new`
+    /// template.
+    EmptyCode,
+}
+
+impl SquatSignal {
+    fn weight(&self) -> u8 {
+        match self {
+            SquatSignal::PlaceholderVersion(_) => 25,
+            SquatSignal::AllYanked => 15,
+            SquatSignal::NoRepository => 15,
+            SquatSignal::NoDescription => 10,
+            SquatSignal::PlaceholderDescription => 30,
+            SquatSignal::FewDownloads(_) => 15,
+            SquatSignal::NeverUpdated => 10,
+            SquatSignal::EmptyCode => 35,
+        }
+    }
+}
+
+impl fmt::Display for SquatSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SquatSignal::PlaceholderVersion(version) => {
+                write!(f, "placeholder version {}", version)
+            }
+            SquatSignal::AllYanked => write!(f, "all versions yanked"),
+            SquatSignal::NoRepository => write!(f, "no repository"),
+            SquatSignal::NoDescription => write!(f, "no description"),
+            SquatSignal::PlaceholderDescription => write!(f, "placeholder description"),
+            SquatSignal::FewDownloads(downloads) => write!(f, "only {} downloads", downloads),
+            SquatSignal::NeverUpdated => write!(f, "never updated"),
+            SquatSignal::EmptyCode => write!(f, "no code"),
+        }
+    }
+}
+
+/// How likely a crate holds its name only to reserve it, see
+/// [`CrateInfo::squat_report`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct SquatReport {
+    /// The likelihood of a squat, from 0 (in use) to 100 (certainly a
+    /// placeholder).
+    pub score: u8,
+
+    /// The hints the score is based on.
+    pub signals: Vec<SquatSignal>,
+}
+
+impl SquatReport {
+    fn new(signals: Vec<SquatSignal>) -> Self {
+        let score = signals
+            .iter()
+            .map(|signal| u32::from(signal.weight()))
+            .sum::<u32>()
+            .min(100) as u8;
+        Self { score, signals }
+    }
+
+    /// Returns `true` if the crate is likely a placeholder.
+    pub fn is_likely(&self) -> bool {
+        self.score >= LIKELY_SCORE
+    }
+}
+
+impl CrateInfo {
+    /// Rates how likely the crate was published only to reserve its name,
+    /// based on its metadata. Unknown fields are not taken into account, the
+    /// report is most meaningful with the metadata of the crates.io API.
+    ///
+    /// [`Checker::squat_report`](crate::Checker::squat_report) additionally
+    /// inspects the published package.
+    pub fn squat_report(&self) -> SquatReport {
+        SquatReport::new(self.squat_signals())
+    }
+
+    fn squat_signals(&self) -> Vec<SquatSignal> {
+        let mut signals = Vec::new();
+        if let Some(version) = self
+            .max_version
+            .as_ref()
+            .filter(|version| version.starts_with("0.0."))
+        {
+            signals.push(SquatSignal::PlaceholderVersion(version.clone()));
+        }
+        if self.yanked == Some(true) {
+            signals.push(SquatSignal::AllYanked);
+        }
+
+        // Backends without manifest data report neither repository nor
+        // update time, a missing repository is only meaningful otherwise.
+        if self.updated_at.is_some() {
+            if self.repository.is_none() {
+                signals.push(SquatSignal::NoRepository);
+            }
+            match &self.description {
+                None => signals.push(SquatSignal::NoDescription),
+                Some(description) if description.trim().is_empty() => {
+                    signals.push(SquatSignal::NoDescription)
+                }
+                Some(description) if is_placeholder(description) => {
+                    signals.push(SquatSignal::PlaceholderDescription)
+                }
+                Some(_) => {}
+            }
+        }
+
+        if let Some(downloads) = self.downloads {
+            let few = match self.recent_downloads {
+                Some(recent) => recent < FEW_RECENT_DOWNLOADS,
+                None => downloads < FEW_DOWNLOADS,
+            };
+            if few {
+                signals.push(SquatSignal::FewDownloads(downloads));
+            }
+        }
+
+        if let (Some(created_at), Some(updated_at)) = (self.created_at, self.updated_at) {
+            let day = Duration::from_secs(24 * 60 * 60);
+            let never_updated = updated_at
+                .duration_since(created_at)
+                .map_or(true, |age| age < day);
+            let old = SystemTime::now()
+                .duration_since(created_at)
+                .is_ok_and(|age| age > 180 * day);
+            if never_updated && old {
+                signals.push(SquatSignal::NeverUpdated);
+            }
+        }
+
+        signals
+    }
+}
+
+/// Extends the metadata based report with the signals found in the
+/// published package `crate_file`, a gzipped tarball.
+pub(crate) fn inspect_crate_file(
+    info: &CrateInfo,
+    crate_file: &[u8],
+) -> Result<SquatReport, Error> {
+    let mut signals = info.squat_signals();
+
+    let mut archive = tar::Archive::new(GzDecoder::new(crate_file));
+    let invalid = |e: std::io::Error| Error::InvalidResponse(format!("invalid crate file: {}", e));
+    let mut code_lines = 0;
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        let is_rust = entry
+            .path()
+            .is_ok_and(|path| path.extension().is_some_and(|ext| ext == "rs"));
+        if !is_rust {
+            continue;
+        }
+
+        let mut contents = String::new();
+        // Skip files that are not valid UTF-8, they can't be code.
+        if entry.read_to_string(&mut contents).is_ok() {
+            code_lines += count_code_lines(&contents);
+        }
+    }
+    if code_lines <= EMPTY_CODE_LINES {
+        signals.push(SquatSignal::EmptyCode);
+    }
+
+    Ok(SquatReport::new(signals))
+}
+
+fn is_placeholder(description: &str) -> bool {
+    let words = description
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let words = format!(" {} ", words);
+    PLACEHOLDER_WORDS
+        .iter()
+        .any(|word| words.contains(&format!(" {} ", word)))
+}
+
+/// Counts the lines of Rust code in `source`, ignoring blank lines, comments,
+/// test modules and the code generated by `cargo new`.
+fn count_code_lines(source: &str) -> usize {
+    let mut lines = 0;
+    let mut in_comment = false;
+    let mut after_cfg_test = false;
+    // The brace depth inside a `#[cfg(test)]` module, if in one.
+    let mut test_depth = None;
+    for line in source.lines().map(str::trim) {
+        if in_comment {
+            in_comment = !line.contains("*/");
+            continue;
+        }
+        if line.starts_with("/*") {
+            in_comment = !line.contains("*/");
+            continue;
+        }
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+
+        if let Some(depth) = test_depth {
+            // The module ends with the brace closing it, its opening brace may
+            // be on the line after `mod tests`.
+            let depth = depth + brace_balance(line);
+            test_depth = Some(depth).filter(|&depth| depth > 0 || !line.contains('}'));
+            continue;
+        }
+        if line == "#[cfg(test)]" {
+            after_cfg_test = true;
+            continue;
+        }
+        if after_cfg_test {
+            after_cfg_test = false;
+            // Skip inline test modules, `mod tests;` only declares one.
+            if line.starts_with("mod ") || line.starts_with("pub mod ") {
+                let depth = brace_balance(line);
+                if depth > 0 || !(line.ends_with(';') || line.ends_with('}')) {
+                    test_depth = Some(depth);
+                }
+                continue;
+            }
+        }
+
+        if !is_template_line(line) {
+            lines += 1;
+        }
+    }
+    lines
+}
+
+/// Returns the number of braces `line` opens minus the number it closes.
+fn brace_balance(line: &str) -> i32 {
+    line.chars()
+        .map(|c| match c {
+            '{' => 1,
+            '}' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Returns `true` for the lines of the `add` function generated by
+/// `cargo new --lib`.
+fn is_template_line(line: &str) -> bool {
+    matches!(
+        line,
+        "pub fn add(left: usize, right: usize) -> usize {"
+            | "pub fn add(left: u64, right: u64) -> u64 {"
+            | "left + right"
+            | "}"
+            | "fn main() {"
+            | "println!(\"Hello, world!\");"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// Creates a gzipped package containing `files`.
+    fn crate_file(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::fast(),
+        ));
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn placeholder() -> CrateInfo {
+        let created_at = SystemTime::now() - 365 * DAY;
+        let mut info = CrateInfo::new("foo");
+        info.max_version = Some("0.0.1".to_string());
+        info.description = Some("Reserved for future use".to_string());
+        info.created_at = Some(created_at);
+        info.updated_at = Some(created_at);
+        info.downloads = Some(300);
+        info.recent_downloads = Some(5);
+        info.yanked = Some(false);
+        info
+    }
+
+    #[test]
+    fn placeholder_crate() {
+        let report = placeholder().squat_report();
+        assert_eq!(
+            report.signals,
+            [
+                SquatSignal::PlaceholderVersion("0.0.1".to_string()),
+                SquatSignal::NoRepository,
+                SquatSignal::PlaceholderDescription,
+                SquatSignal::FewDownloads(300),
+                SquatSignal::NeverUpdated,
+            ]
+        );
+        assert_eq!(report.score, 95);
+        assert!(report.is_likely());
+    }
+
+    #[test]
+    fn crate_in_use() {
+        let mut info = placeholder();
+        info.max_version = Some("1.0.0".to_string());
+        info.repository = Some("https://github.com/foo/foo".to_string());
+        info.description = Some("Does foo things".to_string());
+        info.updated_at = Some(SystemTime::now());
+        info.recent_downloads = Some(5_000);
+        let report = info.squat_report();
+        assert_eq!(report, SquatReport::default());
+        assert!(!report.is_likely());
+    }
+
+    #[test]
+    fn unknown_fields_are_ignored() {
+        // Registry indices know neither manifests nor downloads.
+        let mut info = CrateInfo::new("foo");
+        info.max_version = Some("0.1.0".to_string());
+        info.yanked = Some(true);
+        assert_eq!(info.squat_report().signals, [SquatSignal::AllYanked]);
+    }
+
+    #[test]
+    fn score_is_capped() {
+        let report = SquatReport::new(vec![
+            SquatSignal::EmptyCode,
+            SquatSignal::PlaceholderDescription,
+            SquatSignal::PlaceholderVersion("0.0.0".to_string()),
+            SquatSignal::AllYanked,
+        ]);
+        assert_eq!(report.score, 100);
+    }
+
+    #[test]
+    fn placeholder_descriptions() {
+        assert!(is_placeholder("Placeholder."));
+        assert!(is_placeholder("Coming soon!"));
+        assert!(is_placeholder("WIP: a parser"));
+        assert!(!is_placeholder("Wipes disks securely"));
+        assert!(!is_placeholder("A reservation system"));
+    }
+
+    #[test]
+    fn template_is_not_code() {
+        let source = "pub fn add(left: u64, right: u64) -> u64 {
+    left + right
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let result = add(2, 2);
+        assert_eq!(result, 4);
+    }
+}
+";
+        assert_eq!(count_code_lines(source), 0);
+    }
+
+    #[test]
+    fn code_after_test_module_declaration_is_counted() {
+        let source = "#[cfg(test)]
+mod tests;
+
+pub struct Parser {
+    input: String,
+}
+";
+        assert_eq!(count_code_lines(source), 2);
+    }
+
+    #[test]
+    fn code_after_test_module_is_counted() {
+        let source = "#[cfg(test)]
+mod tests
+{
+    #[test]
+    fn parses() {}
+}
+
+pub fn parse(input: &str) -> usize {
+    input.len()
+}
+";
+        assert_eq!(count_code_lines(source), 2);
+    }
+
+    #[test]
+    fn comments_are_not_code() {
+        let source = "//! Crate docs.
+/* A block
+   comment. */
+/// Docs.
+pub const ANSWER: u8 = 42;
+";
+        assert_eq!(count_code_lines(source), 1);
+    }
+
+    #[test]
+    fn inspect_empty_crate() {
+        let crate_file = crate_file(&[
+            ("foo-0.0.1/Cargo.toml", "[package]\nname = \"foo\""),
+            (
+                "foo-0.0.1/src/main.rs",
+                "fn main() {\n    println!(\"Hello, world!\");\n}\n",
+            ),
+        ]);
+        let report = inspect_crate_file(&placeholder(), &crate_file).unwrap();
+        assert_eq!(report.signals.last(), Some(&SquatSignal::EmptyCode));
+        assert_eq!(report.score, 100);
+    }
+
+    #[test]
+    fn inspect_crate_with_code() {
+        let code = (0..10)
+            .map(|i| format!("pub const C{}: u8 = {};", i, i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let crate_file = crate_file(&[("foo-0.0.1/src/lib.rs", &code)]);
+        let report = inspect_crate_file(&placeholder(), &crate_file).unwrap();
+        assert_eq!(report, placeholder().squat_report());
+    }
+
+    #[test]
+    fn inspect_invalid_crate_file() {
+        assert!(matches!(
+            inspect_crate_file(&placeholder(), b"not a crate file"),
+            Err(Error::InvalidResponse(_))
+        ));
+    }
+}
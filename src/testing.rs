@@ -0,0 +1,146 @@
+//! Helpers shared by the unit tests.
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    path::PathBuf,
+    process,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Returns an empty directory for the test `name`, removing whatever a
+/// previous run left behind.
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("cargo-free-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A response sent by a [`Server`].
+#[derive(Clone, Debug)]
+pub(crate) struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Response {
+    /// A `200 OK` response with the given body.
+    pub(crate) fn ok(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// An empty response with the given status.
+    pub(crate) fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    /// Adds a header to the response.
+    pub(crate) fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A request received by a [`Server`].
+#[derive(Clone, Debug)]
+pub(crate) struct Request {
+    pub(crate) path: String,
+    pub(crate) headers: HashMap<String, String>,
+}
+
+/// A stand-in registry answering requests on a local port with canned
+/// responses, keyed by path. Several responses for the same path are sent in
+/// order, the last one repeatedly. Unknown paths are answered with
+/// `404 Not Found`.
+pub(crate) struct Server {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Server {
+    pub(crate) fn new(routes: Vec<(&str, Response)>) -> Self {
+        let mut responses = HashMap::<_, Vec<_>>::new();
+        for (path, response) in routes {
+            responses
+                .entry(path.to_string())
+                .or_default()
+                .push(response);
+        }
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split(' ').nth(1).unwrap_or_default().to_string();
+                let mut headers = HashMap::new();
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    match line.trim_end().split_once(':') {
+                        Some((name, value)) => {
+                            headers.insert(name.to_lowercase(), value.trim().to_string())
+                        }
+                        None => break,
+                    };
+                }
+
+                let response = match responses.get_mut(&path) {
+                    Some(queue) if queue.len() > 1 => queue.remove(0),
+                    Some(queue) => queue[0].clone(),
+                    None => Response::status(404),
+                };
+                received.lock().unwrap().push(Request { path, headers });
+                let mut head = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    response.status,
+                    response.body.len()
+                );
+                for (name, value) in &response.headers {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                head.push_str("\r\n");
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(response.body.as_bytes());
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Returns the base URL of the server, e.g. `http://127.0.0.1:1234`.
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the requests received so far, in order.
+    pub(crate) fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Returns the paths requested so far, in order.
+    pub(crate) fn paths(&self) -> Vec<String> {
+        self.requests()
+            .into_iter()
+            .map(|request| request.path)
+            .collect()
+    }
+}